@@ -1,19 +1,15 @@
-use nom::{
-    character::complete::{newline, space1, u32},
-    combinator::opt,
-    multi::{many1, separated_list1},
-    sequence::terminated,
-    IResult,
-};
+use advent_of_code::parse::{finish, lines, unsigned, ParseError};
+use advent_of_code::template::SolutionError;
+use nom::{character::complete::space1, multi::separated_list1, IResult};
 
 advent_of_code::solution!(2);
 
-fn parse_input(input: &str) -> IResult<&str, Vec<Vec<u32>>> {
-    many1(terminated(parse_line, opt(newline)))(input)
+fn parse_input(input: &str) -> Result<Vec<Vec<u32>>, ParseError> {
+    finish(input, lines(parse_line))
 }
 
 pub fn parse_line(input: &str) -> IResult<&str, Vec<u32>> {
-    separated_list1(space1, u32)(input)
+    separated_list1(space1, unsigned)(input)
 }
 
 fn absolute_diff(num1: u32, num2: u32) -> u32 {
@@ -65,26 +61,15 @@ fn slice_without_nth(vec: &[u32], n: usize) -> Vec<u32> {
     [before, after].concat()
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    Some(
-        parse_input(input)
-            .unwrap()
-            .1
-            .iter()
-            .filter(|l| is_safe(l))
-            .count() as u32,
-    )
+pub fn part_one(input: &str) -> Result<u32, SolutionError> {
+    Ok(parse_input(input)?.iter().filter(|l| is_safe(l)).count() as u32)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    Some(
-        parse_input(input)
-            .unwrap()
-            .1
-            .iter()
-            .filter(|l| is_safe_with_dampening(l))
-            .count() as u32,
-    )
+pub fn part_two(input: &str) -> Result<u32, SolutionError> {
+    Ok(parse_input(input)?
+        .iter()
+        .filter(|l| is_safe_with_dampening(l))
+        .count() as u32)
 }
 
 #[cfg(test)]
@@ -94,12 +79,12 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(2));
+        assert_eq!(result, Ok(2));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(4));
+        assert_eq!(result, Ok(4));
     }
 }
@@ -1,74 +1,175 @@
 advent_of_code::solution!(3);
 
-use nom::{
-    bytes::complete::tag,
-    character::complete::{char, digit1},
-    combinator::map_res,
-    sequence::separated_pair,
-    IResult,
-};
+use advent_of_code::visualize::flag_once;
+use memchr::memchr;
+
+/// What the program in memory has done so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Machine {
+    pub total: u64,
+    pub enabled: bool,
+}
 
-fn parse_number(input: &str) -> IResult<&str, u32> {
-    map_res(digit1, |s: &str| s.parse::<u32>())(input)
+impl Default for Machine {
+    fn default() -> Self {
+        // at the start, `mul` instructions are enabled
+        Self {
+            total: 0,
+            enabled: true,
+        }
+    }
 }
 
-fn parse_mul(input: &str) -> IResult<&str, u32> {
-    let (input, _) = tag("mul(")(input)?; // Must start with `mul(`
-    let (input, (a, b)) = separated_pair(parse_number, char(','), parse_number)(input)?;
-    let (input, _) = char(')')(input)?; // Must end with `)`
-    Ok((input, a * b))
+/// An instruction written `name(a,b,...)`, with `arity` unsigned arguments and no spaces.
+#[derive(Debug, Clone, Copy)]
+pub struct Instruction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub effect: fn(&mut Machine, &[u32]),
 }
 
-fn parse_do(input: &str) -> IResult<&str, ()> {
-    let (input, _) = tag("do()")(input)?;
-    Ok((input, ()))
+pub const MUL: Instruction = Instruction {
+    name: "mul",
+    arity: 2,
+    effect: |machine, args| {
+        if machine.enabled {
+            machine.total += u64::from(args[0]) * u64::from(args[1]);
+        }
+    },
+};
+
+pub const DO: Instruction = Instruction {
+    name: "do",
+    arity: 0,
+    effect: |machine, _| machine.enabled = true,
+};
+
+pub const DONT: Instruction = Instruction {
+    name: "don't",
+    arity: 0,
+    effect: |machine, _| machine.enabled = false,
+};
+
+/// An instruction found in memory, starting `offset` bytes in.
+#[derive(Debug, Clone)]
+pub struct Token<'a> {
+    pub offset: usize,
+    pub text: &'a str,
+    pub instruction: &'a Instruction,
+    pub args: Vec<u32>,
 }
 
-fn parse_dont(input: &str) -> IResult<&str, ()> {
-    let (input, _) = tag("don't()")(input)?;
-    Ok((input, ()))
+/// Finds the instructions in corrupted memory, in order.
+///
+/// Every instruction has a `(` straight after its name, so the scan jumps from one `(` to the
+/// next and looks back for a name. If names overlap, the longest that parses wins, as it
+/// starts first. Text an instruction was read from isn't looked at again.
+pub struct Tokens<'a> {
+    memory: &'a str,
+    // longest name first, sorted once up front
+    instructions: Vec<&'a Instruction>,
+    position: usize,
 }
 
-fn process_instructions(input: &str, is_part_1: bool) -> u32 {
-    let mut input = input;
-    let mut total = 0;
-    let mut mul_enabled = true; // At the start, `mul` instructions are enabled
-
-    while !input.is_empty() {
-        if !is_part_1 {
-            if let Ok((remaining, _)) = parse_do(input) {
-                mul_enabled = true;
-                input = remaining;
-                continue;
-            }
+impl<'a> Tokens<'a> {
+    pub fn new(memory: &'a str, instructions: &'a [Instruction]) -> Self {
+        let mut instructions: Vec<&'a Instruction> = instructions.iter().collect();
+        instructions.sort_by_key(|instruction| std::cmp::Reverse(instruction.name.len()));
+        Self {
+            memory,
+            instructions,
+            position: 0,
+        }
+    }
 
-            if let Ok((remaining, _)) = parse_dont(input) {
-                mul_enabled = false;
-                input = remaining;
-                continue;
+    // the instruction whose name ends at the `(` at `open`, and where its text ends
+    fn read_at(&self, open: usize) -> Option<(&'a Instruction, Vec<u32>, usize)> {
+        let before = &self.memory[self.position..open];
+        self.instructions
+            .iter()
+            .filter(|instruction| before.ends_with(instruction.name))
+            .find_map(|&instruction| {
+                let (args, end) = read_args(self.memory.as_bytes(), open + 1, instruction.arity)?;
+                Some((instruction, args, end))
+            })
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let bytes = self.memory.as_bytes();
+            let open = self.position + memchr(b'(', &bytes[self.position..])?;
+
+            match self.read_at(open) {
+                Some((instruction, args, end)) => {
+                    let offset = open - instruction.name.len();
+                    self.position = end;
+                    return Some(Token {
+                        offset,
+                        text: &self.memory[offset..end],
+                        instruction,
+                        args,
+                    });
+                }
+                None => self.position = open + 1,
             }
         }
+    }
+}
 
-        if let Ok((remaining, result)) = parse_mul(input) {
-            if mul_enabled {
-                total += result;
-            }
-            input = remaining;
-            continue;
+// `arity` comma separated numbers from `start` and a closing `)`, and the offset after it
+fn read_args(bytes: &[u8], start: usize, arity: usize) -> Option<(Vec<u32>, usize)> {
+    let mut args = Vec::with_capacity(arity);
+    let mut position = start;
+
+    for i in 0..arity {
+        if i > 0 {
+            (bytes.get(position) == Some(&b',')).then_some(())?;
+            position += 1;
         }
+        let digits = bytes[position..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let number = std::str::from_utf8(&bytes[position..position + digits]).ok()?;
+        args.push(number.parse().ok()?);
+        position += digits;
+    }
+
+    (bytes.get(position) == Some(&b')')).then_some((args, position + 1))
+}
 
-        input = &input[1..];
+// `--trace` lists every instruction run, with its byte offset
+#[track_caller]
+fn run(memory: &str, instructions: &[Instruction]) -> Machine {
+    let trace = flag_once("--trace");
+    let mut machine = Machine::default();
+
+    for token in Tokens::new(memory, instructions) {
+        (token.instruction.effect)(&mut machine, &token.args);
+        if trace {
+            println!(
+                "{:>6}  {:<16} total {}{}",
+                token.offset,
+                token.text,
+                machine.total,
+                if machine.enabled { "" } else { ", disabled" }
+            );
+        }
     }
 
-    total
+    machine
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    Some(process_instructions(input, true))
+pub fn part_one(input: &str) -> Option<u64> {
+    Some(run(input, &[MUL]).total)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    Some(process_instructions(input, false))
+pub fn part_two(input: &str) -> Option<u64> {
+    Some(run(input, &[MUL, DO, DONT]).total)
 }
 
 #[cfg(test)]
@@ -90,4 +191,54 @@ mod tests {
         ));
         assert_eq!(result, Some(48));
     }
+
+    #[test]
+    fn test_tokens() {
+        let memory = advent_of_code::template::read_file_part("examples", DAY, 2);
+        let tokens: Vec<(usize, &str)> = Tokens::new(&memory, &[MUL, DO, DONT])
+            .map(|token| (token.offset, token.text))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (1, "mul(2,4)"),
+                (20, "don't()"),
+                (28, "mul(5,5)"),
+                (48, "mul(11,8)"),
+                (59, "do()"),
+                (64, "mul(8,5)"),
+            ]
+        );
+
+        // a new instruction only needs an entry in the table
+        const ADD: Instruction = Instruction {
+            name: "add",
+            arity: 3,
+            effect: |machine, args| {
+                machine.total += args.iter().map(|&a| u64::from(a)).sum::<u64>()
+            },
+        };
+        let tokens: Vec<Token> = Tokens::new("add(1,2)add(1,2,3)mul(4,5)", &[ADD]).collect();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(
+            (tokens[0].offset, tokens[0].args.clone()),
+            (8, vec![1, 2, 3])
+        );
+
+        // `smul` and `mul` share a `(`, and the longer name comes first
+        const SMUL: Instruction = Instruction {
+            name: "smul",
+            ..MUL
+        };
+        let tokens: Vec<&str> = Tokens::new("smul(2,3)", &[MUL, SMUL])
+            .map(|token| token.instruction.name)
+            .collect();
+        assert_eq!(tokens, vec!["smul"]);
+
+        // products are worked out in a u64
+        assert_eq!(
+            part_one("mul(4294967295,4294967295)"),
+            Some(u64::from(u32::MAX) * u64::from(u32::MAX))
+        );
+    }
 }
@@ -1,7 +1,9 @@
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::{Palette, Visualize, Visualizer};
+use hashbrown::HashSet;
 use rayon::prelude::*;
 advent_of_code::solution!(4);
 
-const WORD_CHARS: [char; 4] = ['X', 'M', 'A', 'S'];
 const DIRECTIONS: [(isize, isize); 8] = [
     (0, 1),
     (0, -1),
@@ -13,107 +15,250 @@ const DIRECTIONS: [(isize, isize); 8] = [
     (-1, -1),
 ];
 
-fn find_word_in_grid(grid: &[Vec<char>]) -> usize {
-    let rows = grid.len();
-    let cols = grid[0].len();
+/// Part two's cross of two `MAS`es, in one of its four rotations. `.` matches anything.
+pub const X_MAS: [&str; 3] = ["M.S", ".A.", "M.S"];
 
-    (0..rows)
+type Grid = [Vec<char>];
+
+fn parse_grid(input: &str) -> Vec<Vec<char>> {
+    input.lines().map(|l| l.chars().collect()).collect()
+}
+
+fn size(grid: &Grid) -> (usize, usize) {
+    (grid.first().map_or(0, Vec::len), grid.len())
+}
+
+fn letter_at(grid: &Grid, x: isize, y: isize) -> Option<char> {
+    let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+    grid.get(y)?.get(x).copied()
+}
+
+/// A word found in the grid: which word, where its first letter is, and the way it reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordMatch {
+    pub word: usize,
+    pub start: (usize, usize),
+    pub direction: (isize, isize),
+    pub len: usize,
+}
+
+impl WordMatch {
+    /// The `(x, y)` of each letter, first to last.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.len as isize).map(|i| {
+            (
+                self.start.0.wrapping_add_signed(self.direction.0 * i),
+                self.start.1.wrapping_add_signed(self.direction.1 * i),
+            )
+        })
+    }
+}
+
+/// Every place any of `words` reads in a straight line in one of the eight directions. A
+/// palindrome is found once each way it reads, but a single letter only once.
+pub fn find_words(grid: &Grid, words: &[&str]) -> Vec<WordMatch> {
+    let words: Vec<Vec<char>> = words.iter().map(|w| w.chars().collect()).collect();
+    let (width, height) = size(grid);
+
+    (0..height)
         .into_par_iter()
-        .map(|r| {
-            (0..cols)
-                .map(|c| {
-                    if grid[r][c] == 'X' {
-                        DIRECTIONS
-                            .iter()
-                            .filter(|(dr, dc)| check_word(grid, r as isize, c as isize, dr, dc))
-                            .count()
+        .flat_map_iter(|y| {
+            let words = &words;
+            (0..width).flat_map(move |x| {
+                words.iter().enumerate().flat_map(move |(word, letters)| {
+                    let directions = if letters.len() == 1 {
+                        &DIRECTIONS[..1]
                     } else {
-                        0
-                    }
+                        &DIRECTIONS[..]
+                    };
+                    directions
+                        .iter()
+                        .filter(move |&&(dx, dy)| reads(grid, letters, x, y, dx, dy))
+                        .map(move |&direction| WordMatch {
+                            word,
+                            start: (x, y),
+                            direction,
+                            len: letters.len(),
+                        })
                 })
-                .sum::<usize>()
+            })
+        })
+        .collect()
+}
+
+fn reads(grid: &Grid, letters: &[char], x: usize, y: usize, dx: isize, dy: isize) -> bool {
+    !letters.is_empty()
+        && letters.iter().enumerate().all(|(i, &letter)| {
+            let i = i as isize;
+            letter_at(grid, x as isize + dx * i, y as isize + dy * i) == Some(letter)
+        })
+}
+
+/// A small 2D pattern to look for in the grid, with `None` matching any letter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stencil {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<char>>,
+}
+
+impl Stencil {
+    /// Builds a stencil from its rows, with `.` for a wildcard.
+    pub fn new(rows: &[&str]) -> Result<Self, SolutionError> {
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err(SolutionError::new("a stencil needs at least one cell"));
+        }
+        if let Some(y) = rows.iter().position(|row| row.chars().count() != width) {
+            return Err(SolutionError::new(format!(
+                "stencil row {} is {:?}, but rows should all be {width} wide",
+                y + 1,
+                rows[y]
+            )));
+        }
+
+        Ok(Self {
+            width,
+            height: rows.len(),
+            cells: rows
+                .iter()
+                .flat_map(|row| row.chars().map(|c| (c != '.').then_some(c)))
+                .collect(),
         })
-        .sum()
-}
-
-fn check_word(grid: &[Vec<char>], mut r: isize, mut c: isize, dr: &isize, dc: &isize) -> bool {
-    let offset_amount: isize = (WORD_CHARS.len()) as isize - 1;
-    let ddr = offset_amount * dr;
-    let ddc = offset_amount * dc;
-    if r + ddr < 0
-        || r + ddr >= grid.len() as isize
-        || c + ddc < 0
-        || c + ddc >= grid[0].len() as isize
-    {
-        return false;
     }
-    for &ch in &WORD_CHARS[1..] {
-        r += dr;
-        c += dc;
-        if grid[r as usize][c as usize] != ch {
-            return false;
+
+    fn get(&self, x: usize, y: usize) -> Option<char> {
+        self.cells[y * self.width + x]
+    }
+
+    /// The stencil turned a quarter clockwise.
+    pub fn rotate(&self) -> Self {
+        let cells = (0..self.width)
+            .flat_map(|y| (0..self.height).map(move |x| self.get(y, self.height - 1 - x)))
+            .collect();
+        Self {
+            width: self.height,
+            height: self.width,
+            cells,
         }
     }
-    true
-}
-
-fn find_intersecting_mas(grid: &[Vec<char>]) -> usize {
-    let rows = grid.len();
-    let cols = grid[0].len();
-    let mut count = 0;
-
-    for r in 0..rows {
-        for c in 0..cols {
-            if grid[r][c] == 'A'
-                && (check_mas(grid, r, c, -1, -1) && check_mas(grid, r, c, 1, -1)
-                    || check_mas(grid, r, c, -1, -1) && check_mas(grid, r, c, -1, 1)
-                    || check_mas(grid, r, c, 1, 1) && check_mas(grid, r, c, -1, 1)
-                    || check_mas(grid, r, c, 1, 1) && check_mas(grid, r, c, 1, -1))
-            {
-                count += 1;
+
+    /// The stencil in each of its four rotations, leaving out any that look the same.
+    pub fn rotations(&self) -> Vec<Self> {
+        let mut rotations = vec![self.clone()];
+        for _ in 0..3 {
+            let next = rotations.last().unwrap().rotate();
+            if !rotations.contains(&next) {
+                rotations.push(next);
             }
         }
+        rotations
+    }
+
+    /// The `(x, y)` offsets of the letters that have to match.
+    pub fn letters(&self) -> impl Iterator<Item = ((usize, usize), char)> + '_ {
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| Some(((x, y), self.get(x, y)?)))
     }
 
-    count
+    fn matches_at(&self, grid: &Grid, x: usize, y: usize) -> bool {
+        self.letters().all(|((dx, dy), letter)| {
+            letter_at(grid, (x + dx) as isize, (y + dy) as isize) == Some(letter)
+        })
+    }
 }
 
-fn check_mas(grid: &[Vec<char>], r: usize, c: usize, dr: isize, dc: isize) -> bool {
-    let rows = grid.len();
-    let cols = grid[0].len();
+/// A stencil found in the grid, by its index and the `(x, y)` of its top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilMatch {
+    pub stencil: usize,
+    pub corner: (usize, usize),
+}
 
-    let (r1, c1) = (r as isize + dr, c as isize + dc);
-    let (r2, c2) = (r as isize - dr, c as isize - dc);
+/// Every place one of `stencils` fits over the grid.
+pub fn find_stencils(grid: &Grid, stencils: &[Stencil]) -> Vec<StencilMatch> {
+    let (width, height) = size(grid);
 
-    if r1 >= 0
-        && r1 < rows as isize
-        && c1 >= 0
-        && c1 < cols as isize
-        && r2 >= 0
-        && r2 < rows as isize
-        && c2 >= 0
-        && c2 < cols as isize
-    {
-        grid[r1 as usize][c1 as usize] == 'M' && grid[r2 as usize][c2 as usize] == 'S'
-    } else {
-        false
-    }
+    (0..height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            (0..width).flat_map(move |x| {
+                stencils
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, stencil)| stencil.matches_at(grid, x, y))
+                    .map(move |(stencil, _)| StencilMatch {
+                        stencil,
+                        corner: (x, y),
+                    })
+            })
+        })
+        .collect()
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let grid: Vec<Vec<char>> = input.lines().map(|l| l.chars().collect()).collect();
+/// The grid with the letters of some matches picked out.
+struct Highlight<'a> {
+    grid: &'a Grid,
+    found: HashSet<(usize, usize)>,
+}
 
-    let occurrences = find_word_in_grid(&grid);
+impl Visualize for Highlight<'_> {
+    type Cell = (char, bool);
 
-    Some(occurrences)
+    fn size(&self) -> (usize, usize) {
+        size(self.grid)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Self::Cell {
+        (self.grid[y][x], self.found.contains(&(x, y)))
+    }
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let grid: Vec<Vec<char>> = input.lines().map(|l| l.chars().collect()).collect();
+// draws the grid with `found` highlighted, if `--visualize` was passed
+#[track_caller]
+fn highlight(grid: &Grid, found: impl Iterator<Item = (usize, usize)>) {
+    let letters: HashSet<char> = grid.iter().flatten().copied().collect();
+    let palette = letters
+        .into_iter()
+        .fold(Palette::new('?', [80, 80, 80]), |palette, letter| {
+            palette.with((letter, false), letter, [90, 90, 100]).with(
+                (letter, true),
+                letter,
+                [250, 200, 60],
+            )
+        });
 
-    let occurrences = find_intersecting_mas(&grid);
+    if let Some(mut visualizer) = Visualizer::from_args(DAY, palette) {
+        visualizer.finish(&Highlight {
+            grid,
+            found: found.collect(),
+        });
+    }
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    let grid = parse_grid(input);
+    let found = find_words(&grid, &["XMAS"]);
+
+    highlight(&grid, found.iter().flat_map(WordMatch::cells));
+    Some(found.len())
+}
+
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
+    let grid = parse_grid(input);
+    let stencils = Stencil::new(&X_MAS)?.rotations();
+    let found = find_stencils(&grid, &stencils);
 
-    Some(occurrences)
+    highlight(
+        &grid,
+        found.iter().flat_map(|m| {
+            stencils[m.stencil]
+                .letters()
+                .map(move |((dx, dy), _)| (m.corner.0 + dx, m.corner.1 + dy))
+        }),
+    );
+    Ok(found.len())
 }
 
 #[cfg(test)]
@@ -129,6 +274,51 @@ mod tests {
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(9));
+        assert_eq!(result, Ok(9));
+    }
+
+    #[test]
+    fn test_match_locations() {
+        let grid = parse_grid("XMAS.\n.A...\nS.S..\nA....\nM....\n");
+
+        let found = find_words(&grid, &["XMAS", "SAM", "Z"]);
+        let xmas: Vec<_> = found.iter().filter(|m| m.word == 0).collect();
+        assert_eq!(xmas.len(), 1);
+        assert_eq!(
+            xmas[0].cells().collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (2, 0), (3, 0)]
+        );
+        // SAM reads backwards along the top row and down the left edge
+        let sam: Vec<_> = found
+            .iter()
+            .filter(|m| m.word == 1)
+            .map(|m| m.start)
+            .collect();
+        assert_eq!(sam, vec![(3, 0), (0, 2)]);
+
+        // a plus shape, the same every way round
+        let plus = Stencil::new(&[".A.", "AXA", ".A."]).unwrap();
+        assert_eq!(plus.rotations().len(), 1);
+        let x_mas = Stencil::new(&X_MAS).unwrap();
+        assert_eq!(
+            x_mas.rotate(),
+            Stencil::new(&["M.M", ".A.", "S.S"]).unwrap()
+        );
+        assert_eq!(x_mas.rotations().len(), 4);
+
+        let cross = parse_grid("S.M\n.A.\nS.M\n");
+        assert_eq!(
+            find_stencils(&cross, &x_mas.rotations()),
+            vec![StencilMatch {
+                stencil: 2,
+                corner: (0, 0)
+            }]
+        );
+        let error = Stencil::new(&["MS", "A"]).unwrap_err();
+        assert_eq!(error.location, None);
+        assert_eq!(
+            error.message,
+            "stencil row 2 is \"A\", but rows should all be 2 wide"
+        );
     }
 }
@@ -2,75 +2,192 @@ advent_of_code::solution!(5);
 
 use rayon::prelude::*;
 
-use nom::{
-    bytes::complete::tag,
-    character::complete::{digit1, line_ending},
-    combinator::map_res,
-    multi::{many1, separated_list1},
-    sequence::{separated_pair, terminated},
-    IResult,
-};
-use std::{cmp::Ordering, str::FromStr};
-
-fn parse_number(input: &str) -> IResult<&str, u32> {
-    map_res(digit1, |s: &str| u32::from_str(s))(input)
+use advent_of_code::bitset::BitSet;
+use advent_of_code::parse::{blank_line, finish, lines, list, unsigned, ParseError};
+use advent_of_code::template::SolutionError;
+use hashbrown::HashMap;
+use nom::{character::complete::char, sequence::separated_pair};
+use std::fmt::Display;
+
+type Rules = Vec<(u32, u32)>;
+type Data = Vec<Vec<u32>>;
+// each update with its pages in rule order
+type Sorted = Vec<(Vec<u32>, Vec<u32>)>;
+
+fn parse_input(input: &str) -> Result<(Rules, Data), ParseError> {
+    let rule = separated_pair(unsigned, char('|'), unsigned);
+    let update = list(",", unsigned);
+
+    finish(
+        input,
+        separated_pair(lines(rule), blank_line, lines(update)),
+    )
 }
 
-fn parse_pipe_line(input: &str) -> IResult<&str, (u32, u32)> {
-    terminated(
-        separated_pair(parse_number, tag("|"), parse_number),
-        line_ending,
-    )(input)
+/// Why an update's pages can't be put in one order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderError {
+    /// The same page is printed twice.
+    Repeated(u32),
+    /// No rule decides which of these pages comes first.
+    Unordered(Vec<u32>),
+    /// The rules go round in a loop through these pages, in rule order.
+    Cycle(Vec<u32>),
 }
 
-fn parse_comma_line(input: &str) -> IResult<&str, Vec<u32>> {
-    terminated(separated_list1(tag(","), parse_number), line_ending)(input)
+impl Display for OrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |pages: &[u32], separator: &str| {
+            pages
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        match self {
+            OrderError::Repeated(page) => write!(f, "page {page} appears more than once"),
+            OrderError::Unordered(pages) => write!(
+                f,
+                "no rule orders pages {} against each other",
+                join(pages, ", ")
+            ),
+            OrderError::Cycle(pages) => {
+                write!(
+                    f,
+                    "the rules for pages {} form a cycle",
+                    join(pages, " -> ")
+                )
+            }
+        }
+    }
 }
 
-type Rules = Vec<(u32, u32)>;
-type Data = Vec<Vec<u32>>;
+/// The page ordering rules as a directed graph, with an edge from each page to every page
+/// that has to be printed after it. Pages get dense ids in the order the rules name them.
+#[derive(Debug, Clone)]
+pub struct PageGraph {
+    ids: HashMap<u32, usize>,
+    after: Vec<BitSet>,
+}
+
+impl PageGraph {
+    pub fn new(rules: &[(u32, u32)]) -> Self {
+        let mut ids = HashMap::new();
+        for &page in rules.iter().flat_map(|(a, b)| [a, b]) {
+            let next = ids.len();
+            ids.entry(page).or_insert(next);
+        }
+
+        let mut after = vec![BitSet::new(ids.len()); ids.len()];
+        for (before, later) in rules {
+            after[ids[before]].insert(ids[later]);
+        }
+        Self { ids, after }
+    }
+
+    fn precedes(&self, a: u32, b: u32) -> bool {
+        match (self.ids.get(&a), self.ids.get(&b)) {
+            (Some(&a), Some(&b)) => self.after[a].contains(b),
+            _ => false,
+        }
+    }
+
+    /// Sorts the pages of `update` by the rules between them.
+    ///
+    /// This is a topological sort of the rules restricted to the update's pages, which has
+    /// to find exactly one page with nothing left before it at every step. Otherwise those
+    /// pages aren't totally ordered, or the rules loop and none of them can go first.
+    pub fn sort(&self, update: &[u32]) -> Result<Vec<u32>, OrderError> {
+        if let Some(i) = (1..update.len()).find(|&i| update[..i].contains(&update[i])) {
+            return Err(OrderError::Repeated(update[i]));
+        }
+
+        // earlier[i]: how many unplaced pages of the update must come before update[i]
+        let mut earlier: Vec<usize> = update
+            .iter()
+            .map(|&b| update.iter().filter(|&&a| self.precedes(a, b)).count())
+            .collect();
+        let mut placed = vec![false; update.len()];
+        let mut sorted = Vec::with_capacity(update.len());
+
+        while sorted.len() < update.len() {
+            let ready: Vec<usize> = (0..update.len())
+                .filter(|&i| !placed[i] && earlier[i] == 0)
+                .collect();
+            let next = match ready[..] {
+                [next] => next,
+                [] => return Err(OrderError::Cycle(self.find_cycle(update, &placed))),
+                _ => {
+                    return Err(OrderError::Unordered(
+                        ready.iter().map(|&i| update[i]).collect(),
+                    ))
+                }
+            };
+
+            placed[next] = true;
+            sorted.push(update[next]);
+            for (i, &page) in update.iter().enumerate() {
+                if self.precedes(update[next], page) {
+                    earlier[i] -= 1;
+                }
+            }
+        }
 
-fn parse_input(input: &str) -> IResult<&str, (Rules, Data)> {
-    let (input, pipe_lines) = many1(parse_pipe_line)(input)?;
-    let (input, _) = line_ending(input)?;
-    let (input, comma_lines) = many1(parse_comma_line)(input)?;
-    Ok((input, (pipe_lines, comma_lines)))
+        Ok(sorted)
+    }
+
+    // Every unplaced page has an unplaced page before it, so walking backwards from any of
+    // them has to come round to a page already visited.
+    fn find_cycle(&self, update: &[u32], placed: &[bool]) -> Vec<u32> {
+        let mut walk: Vec<usize> = vec![(0..update.len()).find(|&i| !placed[i]).unwrap()];
+        loop {
+            let current = update[*walk.last().unwrap()];
+            let previous = (0..update.len())
+                .find(|&i| !placed[i] && self.precedes(update[i], current))
+                .unwrap();
+            if let Some(start) = walk.iter().position(|&i| i == previous) {
+                return walk[start..].iter().rev().map(|&i| update[i]).collect();
+            }
+            walk.push(previous);
+        }
+    }
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let (checks, data) = parse_input(input).unwrap().1;
+/// Sorts every update, failing with the line of the first update the rules can't order.
+fn sort_updates(input: &str) -> Result<Sorted, SolutionError> {
+    let (rules, data) = parse_input(input)?;
+    let graph = PageGraph::new(&rules);
+    // the updates start after the rules and the blank line
+    let first_line = rules.len() + 2;
 
-    Some(
-        data.par_iter()
-            .filter(|d| d.is_sorted_by(|x, y| !checks.contains(&(*y, *x))))
-            .map(|d| d[d.len() / 2])
-            .sum(),
-    )
+    let sorted: Vec<Result<Vec<u32>, OrderError>> =
+        data.par_iter().map(|update| graph.sort(update)).collect();
+    // checked in input order, so the same bad line is reported every run
+    data.into_iter()
+        .zip(sorted)
+        .enumerate()
+        .map(|(i, (update, sorted))| {
+            let sorted =
+                sorted.map_err(|e| SolutionError::at_line(first_line + i, 1, e.to_string()))?;
+            Ok((update, sorted))
+        })
+        .collect()
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let (checks, mut data) = parse_input(input).unwrap().1;
+pub fn part_one(input: &str) -> Result<u32, SolutionError> {
+    Ok(sort_updates(input)?
+        .iter()
+        .filter(|(update, sorted)| update == sorted)
+        .map(|(update, _)| update[update.len() / 2])
+        .sum())
+}
 
-    let comparator = |x: &u32, y: &u32| {
-        if checks.contains(&(*y, *x)) {
-            Ordering::Greater
-        } else if checks.contains(&(*x, *y)) {
-            Ordering::Less
-        } else {
-            Ordering::Equal
-        }
-    };
-
-    Some(
-        data.par_iter_mut()
-            .filter(|d| !d.is_sorted_by(|a, b| comparator(a, b) != Ordering::Greater))
-            .map(|d| {
-                d.sort_by(comparator);
-                d
-            })
-            .map(|d| d[d.len() / 2])
-            .sum(),
-    )
+pub fn part_two(input: &str) -> Result<u32, SolutionError> {
+    Ok(sort_updates(input)?
+        .iter()
+        .filter(|(update, sorted)| update != sorted)
+        .map(|(_, sorted)| sorted[sorted.len() / 2])
+        .sum())
 }
 
 #[cfg(test)]
@@ -80,12 +197,44 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(143));
+        assert_eq!(result, Ok(143));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(123));
+        assert_eq!(result, Ok(123));
+    }
+
+    #[test]
+    fn test_bad_orders() {
+        let graph = PageGraph::new(&[(1, 2), (2, 3), (3, 1), (4, 5), (1, 4)]);
+
+        assert_eq!(graph.sort(&[5, 4, 1]), Ok(vec![1, 4, 5]));
+        assert_eq!(graph.sort(&[2, 5]), Err(OrderError::Unordered(vec![2, 5])));
+        assert_eq!(graph.sort(&[4, 1, 4]), Err(OrderError::Repeated(4)));
+        assert_eq!(
+            graph.sort(&[3, 1, 2, 4]),
+            Err(OrderError::Cycle(vec![1, 2, 3]))
+        );
+        // pages 1 and 3 alone are ordered, since the loop goes through 2
+        assert_eq!(graph.sort(&[1, 3]), Ok(vec![3, 1]));
+
+        // page numbers can be anything, and pages no rule names are unordered
+        let graph = PageGraph::new(&[(u32::MAX, 1), (100_000, u32::MAX)]);
+        assert_eq!(
+            graph.sort(&[1, u32::MAX, 100_000]),
+            Ok(vec![100_000, u32::MAX, 1])
+        );
+        assert_eq!(graph.sort(&[1, 7]), Err(OrderError::Unordered(vec![1, 7])));
+
+        let error = part_one("1|2\n2|1\n\n1,2\n").unwrap_err();
+        assert_eq!(error.location, Some((4, 1)));
+        assert_eq!(error.message, "the rules for pages 2 -> 1 form a cycle");
+
+        // the first bad update is the one reported, however many follow
+        let updates = "1,2\n1,1\n".repeat(200);
+        let error = part_two(&format!("1|2\n\n1,2\n2,3\n{updates}")).unwrap_err();
+        assert_eq!(error.location, Some((4, 1)));
     }
 }
@@ -1,9 +1,10 @@
-#![allow(unused_imports)]
-use hashbrown::{HashMap, HashSet};
-use itertools::Itertools;
+use hashbrown::HashMap;
 use rayon::prelude::*;
 
-use aoc_mine::{Coord, Grid, HashGrid, LinearGrid};
+use advent_of_code::bitset::BitSet;
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::{Palette, Visualize, Visualizer};
+use aoc_mine::{Coord, Grid, LinearGrid};
 
 advent_of_code::solution!(6);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -20,7 +21,25 @@ pub enum Direction {
     Left,
 }
 
-const MAX_ITERS: usize = 6000;
+impl Direction {
+    pub fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
+/// What a single step of the guard did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    Moved,
+    Left,
+    /// The guard is back where it already stood, facing the same way, so it never leaves.
+    Looped,
+}
 
 #[derive(Debug, Clone)]
 pub struct State<T: Grid<u8, SquareType>> {
@@ -28,23 +47,18 @@ pub struct State<T: Grid<u8, SquareType>> {
     pub guard_pos: Coord<u8>,
     pub guard_facing: Direction,
     pub visited: HashMap<Coord<u8>, bool>,
-    pub visited_obstacles: HashSet<(Coord<u8>, Direction)>,
+    /// Every (cell, direction) the guard has been in, see [`State::state_index`].
+    pub seen: BitSet,
     pub steps: usize,
     pub width: usize,
     pub height: usize,
 }
 
 impl State<LinearGrid<u8, SquareType>> {
-    pub fn new_from_input(input: &str) -> Self {
+    pub fn new_from_input(input: &str) -> Result<Self, SolutionError> {
         let mut guard_pos: Option<Coord<u8>> = None;
-        let height = input.lines().collect::<Vec<_>>().len();
-        let width = input
-            .lines()
-            .next()
-            .unwrap()
-            .chars()
-            .collect::<Vec<_>>()
-            .len();
+        let height = input.lines().count();
+        let width = input.lines().next().map_or(0, |l| l.chars().count());
         let mut grid: LinearGrid<u8, SquareType> =
             LinearGrid::new(width, height, SquareType::Clear);
         for (y, l) in input.lines().enumerate() {
@@ -56,32 +70,39 @@ impl State<LinearGrid<u8, SquareType>> {
                         guard_pos = Some((x as u8, y as u8).into());
                         SquareType::Clear
                     }
-                    _ => unreachable!("unknown symbol {:?}", c),
+                    _ => {
+                        return Err(SolutionError::at_line(
+                            y + 1,
+                            x + 1,
+                            format!("unknown symbol {c:?}"),
+                        ))
+                    }
                 };
                 let _ = grid.insert((x as u8, y as u8).into(), square_type);
             }
         }
 
-        Self {
+        Ok(Self {
             grid,
-            guard_pos: guard_pos.expect("no guard position found in input"),
+            guard_pos: guard_pos
+                .ok_or_else(|| SolutionError::new("no guard position found in input"))?,
             guard_facing: Direction::Up,
             visited: HashMap::new(),
-            visited_obstacles: HashSet::new(),
+            seen: BitSet::new(width * height * 4),
             steps: 0,
             width,
             height,
-        }
+        })
     }
 }
 impl<T: Grid<u8, SquareType>> State<T> {
     fn turn(&mut self) {
-        self.guard_facing = match self.guard_facing {
-            Direction::Up => Direction::Right,
-            Direction::Right => Direction::Down,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-        }
+        self.guard_facing = self.guard_facing.turn_right();
+    }
+
+    /// Dense index of the guard standing on `cell` facing `facing`.
+    pub fn state_index(&self, cell: Coord<u8>, facing: Direction) -> usize {
+        (cell.1 as usize * self.width + cell.0 as usize) * 4 + facing as usize
     }
 
     fn next_block(&self) -> Option<Coord<u8>> {
@@ -93,185 +114,24 @@ impl<T: Grid<u8, SquareType>> State<T> {
             Direction::Left => current_pos.left(None),
         }
     }
-    fn next_block2(&mut self) -> Option<Coord<u8>> {
-        let current_pos = self.guard_pos;
-        match self.guard_facing {
-            Direction::Up => {
-                let mut updated_guard_pos = None;
-                let next_obstacle_y = (0..current_pos.1)
-                    .rev()
-                    .map(Some)
-                    .chain([None])
-                    .tuple_windows()
-                    .filter_map(|(y, y2)| {
-                        let y = y?;
-                        if y == 0 {
-                            return None;
-                        }
-                        let result = self
-                            .grid
-                            .matches(&(current_pos.0, y).into(), SquareType::Obstacle)
-                            .unwrap_or(false);
-                        if result {
-                            return Some(Coord(current_pos.0, y));
-                        }
-                        let y2 = y2?;
-                        let result2 = self
-                            .grid
-                            .matches(&(current_pos.0, y2).into(), SquareType::Obstacle)
-                            .ok()?;
-
-                        if result2 {
-                            updated_guard_pos = Some(Coord(current_pos.0, y));
-                            Some(Coord(current_pos.0, y2))
-                        } else {
-                            None
-                        }
-                    })
-                    .next();
-
-                if let Some(updated_position) = updated_guard_pos {
-                    self.guard_pos = updated_position;
-                }
-                next_obstacle_y
-            }
-            Direction::Right => {
-                let mut updated_guard_pos = None;
-                let next_obstacle_x = (current_pos.0 as usize + 1..self.width)
-                    .map(Some)
-                    .chain([None])
-                    .tuple_windows()
-                    .filter_map(|(x, x2)| {
-                        let x = x? as u8;
-                        if x == 0 {
-                            return None;
-                        }
-                        let result = self
-                            .grid
-                            .matches(&(x, current_pos.1).into(), SquareType::Obstacle)
-                            .unwrap_or(false);
-                        if result {
-                            return Some(Coord(x, current_pos.1));
-                        }
-                        let x2 = x2? as u8;
-                        let result2 = self
-                            .grid
-                            .matches(&(x2, current_pos.1).into(), SquareType::Obstacle)
-                            .ok()?;
-
-                        if result2 {
-                            updated_guard_pos = Some(Coord(x, current_pos.1));
-                            Some(Coord(x2, current_pos.1))
-                        } else {
-                            None
-                        }
-                    })
-                    .next();
-
-                if let Some(updated_position) = updated_guard_pos {
-                    self.guard_pos = updated_position;
-                }
-                next_obstacle_x
-            }
-            Direction::Down => {
-                let mut updated_guard_pos = None;
-                let next_obstacle_y = (current_pos.1 as usize + 1..self.height)
-                    .map(Some)
-                    .chain([None])
-                    .tuple_windows()
-                    .filter_map(|(y, y2)| {
-                        let y = y? as u8;
-                        if y == 0 {
-                            return None;
-                        }
-                        let result = self
-                            .grid
-                            .matches(&(current_pos.0, y).into(), SquareType::Obstacle)
-                            .unwrap_or(false);
-                        if result {
-                            return Some(Coord(current_pos.0, y));
-                        }
-                        let y2 = y2? as u8;
-                        let result2 = self
-                            .grid
-                            .matches(&(current_pos.0, y2).into(), SquareType::Obstacle)
-                            .ok()?;
-
-                        if result2 {
-                            updated_guard_pos = Some(Coord(current_pos.0, y));
-                            Some(Coord(current_pos.0, y2))
-                        } else {
-                            None
-                        }
-                    })
-                    .next();
-
-                if let Some(updated_position) = updated_guard_pos {
-                    self.guard_pos = updated_position;
-                }
-                next_obstacle_y
-            }
-            Direction::Left => {
-                let mut updated_guard_pos = None;
-                let next_obstacle_x = (0..current_pos.0)
-                    .rev()
-                    .map(Some)
-                    .chain([None])
-                    .tuple_windows()
-                    .filter_map(|(x, x2)| {
-                        let x = x?;
-                        if x == 0 {
-                            return None;
-                        }
-                        let result = self
-                            .grid
-                            .matches(&(x, current_pos.1).into(), SquareType::Obstacle)
-                            .unwrap_or(false);
-                        if result {
-                            return Some(Coord(x, current_pos.1));
-                        }
-                        let x2 = x2?;
-                        let result2 = self
-                            .grid
-                            .matches(&(x2, current_pos.1).into(), SquareType::Obstacle)
-                            .ok()?;
-
-                        if result2 {
-                            updated_guard_pos = Some(Coord(x, current_pos.1));
-                            Some(Coord(x2, current_pos.1))
-                        } else {
-                            None
-                        }
-                    })
-                    .next();
-
-                if let Some(updated_position) = updated_guard_pos {
-                    self.guard_pos = updated_position;
-                }
-                next_obstacle_x
-            }
-        }
-    }
-
     fn next_block_type(&self) -> Option<&SquareType> {
         let next_pos = self.next_block()?;
 
         self.grid.get(&next_pos)
     }
 
-    pub fn step(&mut self) -> bool {
-        self.steps += 1;
-        if self.steps > MAX_ITERS {
-            return false;
+    pub fn step(&mut self) -> Walk {
+        let state = self.state_index(self.guard_pos, self.guard_facing);
+        if self.seen.contains(state) {
+            return Walk::Looped;
         }
+        self.seen.insert(state);
+        self.steps += 1;
         self.visited.entry(self.guard_pos).or_insert(true);
-        let next_block = self.next_block();
-        let next_block_type = self.next_block_type();
-        if next_block.is_none() || next_block_type.is_none() {
-            return false;
-        }
-        let next_block_type = next_block_type.unwrap();
-        let next_block = next_block.unwrap();
+        let (Some(next_block), Some(next_block_type)) = (self.next_block(), self.next_block_type())
+        else {
+            return Walk::Left;
+        };
         match next_block_type {
             SquareType::Clear => {
                 self.guard_pos = next_block;
@@ -281,80 +141,224 @@ impl<T: Grid<u8, SquareType>> State<T> {
             }
         }
 
-        true
+        Walk::Moved
     }
-    pub fn step2(&mut self) -> Option<bool> {
-        self.steps += 1;
-        if self.steps > MAX_ITERS {
-            return Some(false);
-        }
-        let next_block = self.next_block2();
-        if next_block.is_none() {
-            return Some(false);
-        }
-        self.visited.entry(self.guard_pos).or_insert(true);
-        let next_block_type = self.next_block_type();
 
-        if next_block_type.is_none() {
-            return Some(false);
+    pub fn count_visited(&self) -> usize {
+        self.visited.iter().filter(|(_, val)| **val).count()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Clear,
+    Obstacle,
+    Visited,
+    Guard,
+}
+
+impl<T: Grid<u8, SquareType>> Visualize for State<T> {
+    type Cell = Cell;
+
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Cell {
+        let coord: Coord<u8> = (x as u8, y as u8).into();
+        if coord == self.guard_pos {
+            Cell::Guard
+        } else if self.grid.get(&coord) == Some(&SquareType::Obstacle) {
+            Cell::Obstacle
+        } else if self.visited.contains_key(&coord) {
+            Cell::Visited
+        } else {
+            Cell::Clear
         }
+    }
+}
 
-        match next_block_type? {
-            SquareType::Clear => {
-                self.guard_pos = next_block.unwrap();
+fn palette() -> Palette<Cell> {
+    Palette::new('.', [40, 40, 48])
+        .with(Cell::Obstacle, '#', [200, 200, 200])
+        .with(Cell::Visited, 'X', [90, 140, 220])
+        .with(Cell::Guard, '^', [250, 80, 60])
+}
+
+// marks a walk that runs off the map
+const NO_OBSTACLE: u32 = u32::MAX;
+
+/// For every cell and direction, the first obstacle the guard walks into from there.
+///
+/// One extra obstruction is patched in at lookup time, so the table is shared by every
+/// candidate in part two.
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+    width: usize,
+    next_obstacle: Vec<[u32; 4]>,
+}
+
+impl JumpTable {
+    pub fn new<T: Grid<u8, SquareType>>(state: &State<T>) -> Self {
+        let (width, height) = (state.width, state.height);
+        let is_obstacle = |x: usize, y: usize| {
+            state.grid.get(&(x as u8, y as u8).into()) == Some(&SquareType::Obstacle)
+        };
+        let mut next_obstacle = vec![[NO_OBSTACLE; 4]; width * height];
+
+        // sweep each row and column both ways, carrying the last obstacle passed
+        for x in 0..width {
+            let mut up = NO_OBSTACLE;
+            for y in 0..height {
+                next_obstacle[y * width + x][Direction::Up as usize] = up;
+                if is_obstacle(x, y) {
+                    up = (y * width + x) as u32;
+                }
             }
-            SquareType::Obstacle => {
-                match self
-                    .visited_obstacles
-                    .insert((self.guard_pos, self.guard_facing))
-                {
-                    false => return Some(true),
-                    true => self.turn(),
+            let mut down = NO_OBSTACLE;
+            for y in (0..height).rev() {
+                next_obstacle[y * width + x][Direction::Down as usize] = down;
+                if is_obstacle(x, y) {
+                    down = (y * width + x) as u32;
+                }
+            }
+        }
+        for y in 0..height {
+            let mut left = NO_OBSTACLE;
+            for x in 0..width {
+                next_obstacle[y * width + x][Direction::Left as usize] = left;
+                if is_obstacle(x, y) {
+                    left = (y * width + x) as u32;
+                }
+            }
+            let mut right = NO_OBSTACLE;
+            for x in (0..width).rev() {
+                next_obstacle[y * width + x][Direction::Right as usize] = right;
+                if is_obstacle(x, y) {
+                    right = (y * width + x) as u32;
                 }
             }
         }
 
-        None
+        Self {
+            width,
+            next_obstacle,
+        }
     }
 
-    pub fn count_visited(&self) -> usize {
-        self.visited.iter().filter(|(_, val)| **val).count()
+    // whether `other` lies strictly ahead of `cell` when facing `facing`
+    fn is_ahead(&self, cell: usize, facing: Direction, other: usize) -> bool {
+        let same_row = cell / self.width == other / self.width;
+        let same_column = cell % self.width == other % self.width;
+        match facing {
+            Direction::Up => same_column && other < cell,
+            Direction::Down => same_column && other > cell,
+            Direction::Left => same_row && other < cell,
+            Direction::Right => same_row && other > cell,
+        }
+    }
+
+    /// The obstacle hit walking from `cell` towards `facing`, with `extra` counting as an
+    /// obstacle too, or `None` if the guard walks off the map.
+    pub fn next_obstacle(
+        &self,
+        cell: usize,
+        facing: Direction,
+        extra: Option<usize>,
+    ) -> Option<usize> {
+        let stored = self.next_obstacle[cell][facing as usize];
+        let stored = (stored != NO_OBSTACLE).then_some(stored as usize);
+        match extra.filter(|&extra| self.is_ahead(cell, facing, extra)) {
+            // along one row or column, the index difference orders cells by distance
+            Some(extra) => match stored {
+                Some(stored) if stored.abs_diff(cell) < extra.abs_diff(cell) => Some(stored),
+                _ => Some(extra),
+            },
+            None => stored,
+        }
+    }
+
+    // the cell the guard stops on in front of `obstacle`
+    fn stop_before(&self, obstacle: usize, facing: Direction) -> usize {
+        match facing {
+            Direction::Up => obstacle + self.width,
+            Direction::Down => obstacle - self.width,
+            Direction::Left => obstacle + 1,
+            Direction::Right => obstacle - 1,
+        }
+    }
+
+    /// Whether the guard, starting on `start` facing `facing`, walks in a loop once `extra`
+    /// is blocked. It only stops at obstacles, and a stop it has already made facing the
+    /// same way means it is going round again. `seen` is scratch space of `4 * cells` bits.
+    pub fn loops(&self, start: usize, facing: Direction, extra: usize, seen: &mut BitSet) -> bool {
+        seen.clear();
+        let (mut cell, mut facing) = (start, facing);
+        while let Some(obstacle) = self.next_obstacle(cell, facing, Some(extra)) {
+            cell = self.stop_before(obstacle, facing);
+            let state = cell * 4 + facing as usize;
+            if seen.contains(state) {
+                return true;
+            }
+            seen.insert(state);
+            facing = facing.turn_right();
+        }
+        false
     }
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut state = State::new_from_input(input);
-    while state.step() {
-        // loop until it leaves the area
+pub fn part_one(input: &str) -> Result<usize, SolutionError> {
+    let mut state = State::new_from_input(input)?;
+    let mut visualizer = Visualizer::from_args(DAY, palette());
+    let walk = loop {
+        match state.step() {
+            Walk::Moved => {
+                if let Some(visualizer) = visualizer.as_mut() {
+                    visualizer.frame(&state);
+                }
+            }
+            walk => break walk,
+        }
+    };
+    if let Some(visualizer) = visualizer.as_mut() {
+        visualizer.finish(&state);
     }
-    Some(state.count_visited())
+    if walk == Walk::Looped {
+        return Err(SolutionError::new(
+            "the guard walks in a loop and never leaves",
+        ));
+    }
+    Ok(state.count_visited())
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let state = State::new_from_input(input);
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
+    let state = State::new_from_input(input)?;
     let mut check_state = state.clone();
-    while check_state.step() {
+    while check_state.step() == Walk::Moved {
         // run once to find visited areas, since those are the
         // only places we could place an obstruction that would
         // change the path
     }
-    let clear_areas: Vec<Coord<u8>> = check_state.visited.keys().copied().collect();
-
-    let valid_loops = clear_areas
+    let jumps = JumpTable::new(&state);
+    let cell_index = |c: &Coord<u8>| c.1 as usize * state.width + c.0 as usize;
+    let start = cell_index(&state.guard_pos);
+    let candidates: Vec<usize> = check_state
+        .visited
+        .keys()
+        .map(cell_index)
+        .filter(|&cell| cell != start)
+        .collect();
+
+    let valid_loops = candidates
         .par_iter()
-        .filter(|coord| {
-            let mut state = state.clone();
-
-            let _ = state.grid.insert(**coord, SquareType::Obstacle);
-            loop {
-                if let Some(is_loop) = state.step2() {
-                    return is_loop;
-                }
-            }
-        })
+        .map_init(
+            || BitSet::new(state.width * state.height * 4),
+            |seen, &extra| jumps.loops(start, state.guard_facing, extra, seen),
+        )
+        .filter(|&is_loop| is_loop)
         .count();
 
-    Some(valid_loops)
+    Ok(valid_loops)
 }
 
 #[cfg(test)]
@@ -364,12 +368,55 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(41));
+        assert_eq!(result, Ok(41));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(6));
+        assert_eq!(result, Ok(6));
+    }
+
+    #[test]
+    fn test_jump_table() {
+        let state =
+            State::new_from_input(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+        let jumps = JumpTable::new(&state);
+        let index = |x: usize, y: usize| y * state.width + x;
+
+        // the guard at (4, 6) walks up into the obstacle at (4, 0)
+        assert_eq!(
+            jumps.next_obstacle(index(4, 6), Direction::Up, None),
+            Some(index(4, 0))
+        );
+        // a closer extra obstruction wins, one behind or off to the side doesn't
+        assert_eq!(
+            jumps.next_obstacle(index(4, 6), Direction::Up, Some(index(4, 3))),
+            Some(index(4, 3))
+        );
+        assert_eq!(
+            jumps.next_obstacle(index(4, 6), Direction::Up, Some(index(4, 8))),
+            Some(index(4, 0))
+        );
+        assert_eq!(
+            jumps.next_obstacle(index(4, 6), Direction::Up, Some(index(5, 3))),
+            Some(index(4, 0))
+        );
+        assert_eq!(
+            jumps.next_obstacle(index(0, 0), Direction::Left, None),
+            None
+        );
+
+        // the first loop from the puzzle text, and a cell that doesn't make one
+        let mut seen = BitSet::new(state.width * state.height * 4);
+        let start = index(4, 6);
+        assert!(jumps.loops(start, Direction::Up, index(3, 6), &mut seen));
+        assert!(!jumps.loops(start, Direction::Up, index(1, 1), &mut seen));
+    }
+
+    #[test]
+    fn test_guard_in_a_loop() {
+        let result = part_one(".#..\n.^.#\n#...\n..#.\n");
+        assert!(result.is_err());
     }
 }
@@ -1,5 +1,7 @@
 advent_of_code::solution!(7);
 
+use advent_of_code::visualize::flag_once;
+use itertools::Itertools;
 use rayon::prelude::*;
 
 fn parse_lines_iter(input: &str) -> impl ParallelIterator<Item = (u64, Vec<u64>)> + '_ {
@@ -16,70 +18,170 @@ fn parse_lines_iter(input: &str) -> impl ParallelIterator<Item = (u64, Vec<u64>)
     })
 }
 
-fn find_operator_order(test_val: u64, nums: &[u64], idx: usize, current: u64, part2: bool) -> bool {
-    if current > test_val {
-        return false;
-    }
+/// An operator that can sit between two numbers. Equations are evaluated left to right on
+/// `u64`s, and an operator that would overflow or drop below zero can't be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Multiply,
+    Concatenate,
+    Subtract,
+}
 
-    if idx == nums.len() {
-        return current == test_val;
+/// What the left operand must have been, given the result and the right operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Undo {
+    Impossible,
+    From(u64),
+    /// Multiplying by zero gives zero whatever came before.
+    Anything,
+}
+
+impl Operator {
+    pub fn apply(self, a: u64, b: u64) -> Option<u64> {
+        match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Multiply => a.checked_mul(b),
+            Operator::Concatenate => a.checked_mul(10u64.checked_pow(digits(b))?)?.checked_add(b),
+            Operator::Subtract => a.checked_sub(b),
+        }
     }
 
-    let next = nums[idx];
+    // Runs `apply` backwards. This is where the right-to-left search prunes: a product has to
+    // divide evenly, and a concatenation has to end in the digits of `b`.
+    fn undo(self, result: u64, b: u64) -> Undo {
+        let left = match self {
+            Operator::Add => result.checked_sub(b),
+            Operator::Multiply if b == 0 => {
+                return if result == 0 {
+                    Undo::Anything
+                } else {
+                    Undo::Impossible
+                };
+            }
+            Operator::Multiply => result.is_multiple_of(b).then(|| result / b),
+            Operator::Concatenate => 10u64
+                .checked_pow(digits(b))
+                .filter(|&shift| result % shift == b)
+                .map(|shift| result / shift),
+            Operator::Subtract => result.checked_add(b),
+        };
+        left.map_or(Undo::Impossible, Undo::From)
+    }
 
-    // multiply
-    if find_operator_order(test_val, nums, idx + 1, current * next, part2) {
-        return true;
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Multiply => "*",
+            Operator::Concatenate => "||",
+            Operator::Subtract => "-",
+        }
     }
+}
 
-    // add
-    if find_operator_order(test_val, nums, idx + 1, current + next, part2) {
-        return true;
+fn digits(n: u64) -> u32 {
+    n.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// Evaluates `nums` left to right with `operators` between them.
+pub fn evaluate(nums: &[u64], operators: &[Operator]) -> Option<u64> {
+    let (&first, rest) = nums.split_first()?;
+    rest.iter()
+        .zip(operators)
+        .try_fold(first, |value, (&b, op)| op.apply(value, b))
+}
+
+/// Finds operators from `allowed` that make `nums` evaluate to `target`, working from the
+/// last number back so each operator can rule itself out straight away.
+pub fn solve(target: u64, nums: &[u64], allowed: &[Operator]) -> Option<Vec<Operator>> {
+    let (&last, rest) = nums.split_last()?;
+    if rest.is_empty() {
+        return (last == target).then(Vec::new);
     }
 
-    // concatenate (part 2 only)
-    if part2 {
-        let concat = concatenate(current, next);
-        if find_operator_order(test_val, nums, idx + 1, concat, part2) {
-            return true;
-        }
+    allowed.iter().find_map(|&op| {
+        let mut operators = match op.undo(target, last) {
+            Undo::Impossible => return None,
+            Undo::From(left) => solve(left, rest, allowed)?,
+            Undo::Anything => any_operators(rest, allowed, Vec::new())?,
+        };
+        operators.push(op);
+        Some(operators)
+    })
+}
+
+// any operators that evaluate `nums` without overflowing or going negative
+fn any_operators(
+    nums: &[u64],
+    allowed: &[Operator],
+    chosen: Vec<Operator>,
+) -> Option<Vec<Operator>> {
+    if chosen.len() + 1 >= nums.len() {
+        return evaluate(nums, &chosen).map(|_| chosen);
     }
+    allowed.iter().find_map(|&op| {
+        let mut next = chosen.clone();
+        next.push(op);
+        evaluate(&nums[..next.len() + 1], &next)?;
+        any_operators(nums, allowed, next)
+    })
+}
 
-    false
+/// Writes a solved equation out, e.g. `3267: 81 + 40 * 27`.
+pub fn format_equation(target: u64, nums: &[u64], operators: &[Operator]) -> String {
+    let mut terms = vec![nums[0].to_string()];
+    for (num, op) in nums[1..].iter().zip(operators) {
+        terms.push(format!("{} {num}", op.symbol()));
+    }
+    format!("{target}: {}", terms.join(" "))
 }
 
-/// Counts the digits in the second number, then shifts the first number
-/// over by the same amount and adds the second number where there are now zeroes
-///
-/// 123, 456 becomes
-/// 123000 + 456 -> 123456
-fn concatenate(a: u64, b: u64) -> u64 {
-    let digits = b.ilog10() + 1;
-    a * 10u64.pow(digits) + b
+// `--operators` prints the operators found for every equation that can be solved
+#[track_caller]
+fn calibration_total(input: &str, allowed: &[Operator]) -> u64 {
+    let solved: Vec<(u64, Vec<u64>, Vec<Operator>)> = parse_lines_iter(input)
+        .filter_map(|(test, nums)| {
+            let operators = solve(test, &nums, allowed)?;
+            Some((test, nums, operators))
+        })
+        .collect();
+
+    if flag_once("--operators") {
+        let allowed = allowed.iter().map(|op| op.symbol()).join(" ");
+        println!("With {allowed}:");
+        for (test, nums, operators) in &solved {
+            println!("{}", format_equation(*test, nums, operators));
+        }
+    }
+
+    solved.iter().map(|(test, _, _)| test).sum()
 }
 
 pub fn part_one(input: &str) -> Option<u64> {
-    Some(
-        parse_lines_iter(input)
-            .filter(|(test, nums)| find_operator_order(*test, nums, 1, nums[0], false))
-            .map(|(test, _)| test)
-            .sum(),
-    )
+    Some(calibration_total(
+        input,
+        &[Operator::Add, Operator::Multiply],
+    ))
 }
 
 pub fn part_two(input: &str) -> Option<u64> {
-    Some(
-        parse_lines_iter(input)
-            .filter(|(test, nums)| find_operator_order(*test, nums, 1, nums[0], true))
-            .map(|(test, _)| test)
-            .sum(),
-    )
+    Some(calibration_total(
+        input,
+        &[Operator::Add, Operator::Multiply, Operator::Concatenate],
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const ALL: [Operator; 4] = [
+        Operator::Add,
+        Operator::Multiply,
+        Operator::Concatenate,
+        Operator::Subtract,
+    ];
+
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
@@ -91,4 +193,70 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(11487));
     }
+
+    #[test]
+    fn test_operator_assignment() {
+        let operators = solve(
+            7290,
+            &[6, 8, 6, 15],
+            &[Operator::Add, Operator::Multiply, Operator::Concatenate],
+        )
+        .unwrap();
+        assert_eq!(
+            format_equation(7290, &[6, 8, 6, 15], &operators),
+            "7290: 6 * 8 || 6 * 15"
+        );
+
+        assert_eq!(
+            solve(2, &[5, 3], &[Operator::Subtract]),
+            Some(vec![Operator::Subtract])
+        );
+        assert_eq!(solve(2, &[3, 5], &[Operator::Subtract]), None);
+        assert_eq!(
+            solve(0, &[3, 4, 0], &[Operator::Add, Operator::Multiply]),
+            Some(vec![Operator::Add, Operator::Multiply])
+        );
+        assert_eq!(
+            solve(105, &[10, 5], &[Operator::Concatenate])
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            solve(100, &[10, 0], &[Operator::Concatenate])
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut state: u64 = 0x2024_0007;
+        let mut next = |bound: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % bound
+        };
+
+        for _ in 0..2000 {
+            let nums: Vec<u64> = (0..2 + next(4)).map(|_| next(12)).collect();
+            let target = next(200);
+            let allowed = &ALL[..2 + next(3) as usize];
+
+            let brute_force = std::iter::repeat_n(allowed.iter().copied(), nums.len() - 1)
+                .multi_cartesian_product()
+                .any(|ops| evaluate(&nums, &ops) == Some(target));
+            let found = solve(target, &nums, allowed);
+            assert_eq!(
+                found.is_some(),
+                brute_force,
+                "{target}: {nums:?} with {allowed:?}"
+            );
+            if let Some(ops) = found {
+                assert_eq!(evaluate(&nums, &ops), Some(target));
+            }
+        }
+    }
 }
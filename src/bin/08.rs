@@ -1,20 +1,12 @@
 advent_of_code::solution!(8);
 
+use advent_of_code::math::gcd;
 use aoc_mine::Coord;
 use hashbrown::HashSet;
 use rayon::prelude::*;
 
 use hashbrown::HashMap;
 
-fn gcd(mut a: isize, mut b: isize) -> isize {
-    while b != 0 {
-        let r = a % b;
-        a = b;
-        b = r;
-    }
-    a.abs()
-}
-
 fn parse_antennas(input: &str) -> (isize, isize, HashMap<char, Vec<Coord<isize>>>) {
     let mut map: HashMap<char, Vec<Coord<isize>>> = HashMap::new();
     let mut height = 0;
@@ -1,5 +1,8 @@
 advent_of_code::solution!(9);
 
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
 pub fn part_one(input: &str) -> Option<usize> {
     let mut checksum: usize = 0;
     let mut index = 0;
@@ -45,42 +48,49 @@ pub fn part_one(input: &str) -> Option<usize> {
     Some(checksum)
 }
 
+// Each file moves to the leftmost free span that fits it, so the spans are kept in one
+// min-heap of start positions per size. A span only ever shrinks, and the space a file
+// leaves behind is to the right of every file still to move, so it is never reused.
 pub fn part_two(input: &str) -> Option<usize> {
-    let mut input: Vec<usize> = input
+    let disk_map: Vec<usize> = input
         .trim_end()
-        .chars()
-        .map(|ch| ch.to_digit(10).unwrap() as usize)
+        .bytes()
+        .map(|b| (b - b'0') as usize)
         .collect();
 
-    let input_length = input.len();
-
-    let mut checksum: usize = 0;
-
-    let mut free_space_index = vec![0; input_length];
-    for i in 1..input_length {
-        free_space_index[i] = free_space_index[i - 1] + input[i - 1];
+    let mut starts = Vec::with_capacity(disk_map.len());
+    let mut position = 0;
+    for &length in &disk_map {
+        starts.push(position);
+        position += length;
     }
 
-    for right_ptr in (0..input_length).rev().step_by(2) {
-        let mut free_space_found = false;
-
-        for left_ptr in (1..right_ptr).step_by(2) {
-            if input[left_ptr] >= input[right_ptr] {
-                for i in 0..input[right_ptr] {
-                    checksum += (right_ptr / 2) * (free_space_index[left_ptr] + i);
-                }
-                input[left_ptr] -= input[right_ptr];
-                free_space_index[left_ptr] += input[right_ptr];
-                free_space_found = true;
-                break;
-            }
+    let mut free_spans: [BinaryHeap<Reverse<usize>>; 10] = Default::default();
+    for i in (1..disk_map.len()).step_by(2) {
+        if disk_map[i] > 0 {
+            free_spans[disk_map[i]].push(Reverse(starts[i]));
         }
+    }
 
-        if !free_space_found {
-            for i in 0..input[right_ptr] {
-                checksum += (right_ptr / 2) * (free_space_index[right_ptr] + i);
+    let mut checksum = 0;
+    for file in (0..disk_map.len()).step_by(2).rev() {
+        let length = disk_map[file];
+        let mut position = starts[file];
+
+        let leftmost_fit = (length..free_spans.len())
+            .filter_map(|size| free_spans[size].peek().map(|&Reverse(start)| (start, size)))
+            .min()
+            .filter(|&(start, _)| start < position);
+        if let Some((start, size)) = leftmost_fit {
+            free_spans[size].pop();
+            if size > length {
+                free_spans[size - length].push(Reverse(start + length));
             }
+            position = start;
         }
+
+        // the file covers position..position + length
+        checksum += (file / 2) * (position * length + length * length.saturating_sub(1) / 2);
     }
 
     Some(checksum)
@@ -101,4 +111,60 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(2858));
     }
+
+    // lays out every block and moves whole files one at a time, as the puzzle describes
+    fn reference_part_two(disk_map: &[u8]) -> usize {
+        let mut blocks: Vec<Option<usize>> = vec![];
+        for (i, &length) in disk_map.iter().enumerate() {
+            let contents = if i % 2 == 0 { Some(i / 2) } else { None };
+            blocks.extend(std::iter::repeat_n(contents, length as usize));
+        }
+
+        for id in (0..disk_map.len().div_ceil(2)).rev() {
+            let length = disk_map[id * 2] as usize;
+            let Some(file_start) = blocks.iter().position(|&b| b == Some(id)) else {
+                continue;
+            };
+            let free_start = (0..file_start).find(|&start| {
+                start + length <= file_start
+                    && blocks[start..start + length].iter().all(Option::is_none)
+            });
+            if let Some(free_start) = free_start {
+                blocks[free_start..free_start + length].fill(Some(id));
+                blocks[file_start..file_start + length].fill(None);
+            }
+        }
+
+        blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| b.map(|id| i * id))
+            .sum()
+    }
+
+    #[test]
+    fn test_part_two_matches_reference() {
+        // a small LCG is enough to shake out ordering mistakes
+        let mut state: u64 = 0x2024_0009;
+        let mut digit = |low: u8| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            low + ((state >> 33) % (10 - low as u64)) as u8
+        };
+
+        for _ in 0..500 {
+            let files = 1 + digit(0) as usize * 4;
+            let disk_map: Vec<u8> = (0..files * 2 - 1)
+                .map(|i| if i % 2 == 0 { digit(1) } else { digit(0) })
+                .collect();
+            let input: String = disk_map.iter().map(|d| char::from(b'0' + d)).collect();
+
+            assert_eq!(
+                part_two(&input),
+                Some(reference_part_two(&disk_map)),
+                "disk map {input}"
+            );
+        }
+    }
 }
@@ -1,4 +1,7 @@
-use cached::proc_macro::cached;
+use advent_of_code::template::{arg_value, SolutionError};
+use advent_of_code::visualize::first_run;
+use hashbrown::{HashMap, HashSet};
+use num::BigUint;
 
 advent_of_code::solution!(11);
 
@@ -14,30 +17,107 @@ pub fn is_even(stone: u64) -> bool {
     (stone.checked_ilog10().unwrap_or(0) + 1).is_multiple_of(2)
 }
 
-pub fn process_stone(stone: u64) -> (u64, Option<u64>) {
+/// What a stone turns into after one blink, or `None` if it outgrows a `u64`.
+pub fn process_stone(stone: u64) -> Option<(u64, Option<u64>)> {
     if stone == 0 {
-        return (1, None);
+        return Some((1, None));
     }
     if is_even(stone) {
-        return split_stone(stone);
+        return Some(split_stone(stone));
     }
-    (stone * 2024, None)
+    Some((stone.checked_mul(2024)?, None))
 }
 
-#[cached]
-pub fn count_stones(stone: u64, count: u8) -> u64 {
-    let mut found_count: u64 = 1;
-    let mut current = stone;
+/// Stones as a count per engraved value. Stones with the same value change the same way, so
+/// each blink only has to work out the distinct values, however many stones there are.
+#[derive(Debug, Clone)]
+pub struct Blinks {
+    stones: HashMap<u64, BigUint>,
+    blinks: usize,
+    // every value seen so far, and the blink that first added nothing to it
+    seen: HashSet<u64>,
+    closed_at: Option<usize>,
+}
+
+impl Blinks {
+    pub fn new(stones: &[u64]) -> Self {
+        let mut counts: HashMap<u64, BigUint> = HashMap::new();
+        for &stone in stones {
+            *counts.entry(stone).or_default() += 1u32;
+        }
+        Self {
+            seen: counts.keys().copied().collect(),
+            stones: counts,
+            blinks: 0,
+            closed_at: None,
+        }
+    }
 
-    (0..count).rev().for_each(|c| {
-        let (updated, right) = process_stone(current);
-        current = updated;
-        if let Some(right) = right {
-            found_count += count_stones(right, c);
+    pub fn blink(&mut self) -> Result<(), SolutionError> {
+        let mut next: HashMap<u64, BigUint> = HashMap::with_capacity(self.stones.len() * 2);
+        for (&stone, count) in &self.stones {
+            let (left, right) = process_stone(stone).ok_or_else(|| {
+                SolutionError::new(format!(
+                    "stone {stone} outgrows a u64 on blink {}",
+                    self.blinks + 1
+                ))
+            })?;
+            for value in std::iter::once(left).chain(right) {
+                *next.entry(value).or_default() += count;
+            }
         }
-    });
 
-    found_count
+        self.blinks += 1;
+        let before = self.seen.len();
+        self.seen.extend(next.keys().copied());
+        if self.closed_at.is_none() && self.seen.len() == before {
+            self.closed_at = Some(self.blinks);
+        }
+        self.stones = next;
+        Ok(())
+    }
+
+    pub fn run(&mut self, blinks: usize) -> Result<(), SolutionError> {
+        (0..blinks).try_for_each(|_| self.blink())
+    }
+
+    pub fn blinks(&self) -> usize {
+        self.blinks
+    }
+
+    pub fn total(&self) -> BigUint {
+        self.stones.values().sum()
+    }
+
+    /// Distinct values on the stones right now.
+    pub fn distinct(&self) -> usize {
+        self.stones.len()
+    }
+
+    /// Distinct values on the stones at any blink so far.
+    pub fn distinct_seen(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// The first blink that brought up no new value. Every value after it is one already
+    /// seen, so from then on [`Blinks::distinct_seen`] is final.
+    pub fn closed_at(&self) -> Option<usize> {
+        self.closed_at
+    }
+}
+
+// what `--blinks` reports about the distinct values
+fn describe(blinks: &Blinks) -> String {
+    let growth = match blinks.closed_at() {
+        Some(step) => format!("No new values appear from blink {step} on"),
+        None => "New values are still appearing".to_string(),
+    };
+    format!(
+        "{} distinct values after {} blinks, {} seen in all\n{growth}",
+        blinks.distinct(),
+        blinks.blinks(),
+        blinks.distinct_seen()
+    )
 }
 
 pub fn parse_stones(input: &str) -> Vec<u64> {
@@ -48,22 +128,23 @@ pub fn parse_stones(input: &str) -> Vec<u64> {
         .collect()
 }
 
-pub fn part_one(input: &str) -> Option<u64> {
-    Some(
-        parse_stones(input)
-            .iter()
-            .map(|n| count_stones(*n, 25))
-            .sum(),
-    )
+pub fn part_one(input: &str) -> Result<BigUint, SolutionError> {
+    let mut blinks = Blinks::new(&parse_stones(input));
+    blinks.run(25)?;
+    Ok(blinks.total())
 }
 
-pub fn part_two(input: &str) -> Option<u64> {
-    Some(
-        parse_stones(input)
-            .iter()
-            .map(|n| count_stones(*n, 75))
-            .sum(),
-    )
+// `--blinks <n>` blinks `n` times instead of 75, and reports the distinct values
+pub fn part_two(input: &str) -> Result<BigUint, SolutionError> {
+    let count = arg_value("--blinks")?;
+    let mut blinks = Blinks::new(&parse_stones(input));
+    blinks.run(count.unwrap_or(75))?;
+
+    if count.is_some() && first_run() {
+        println!("{}", describe(&blinks));
+    }
+
+    Ok(blinks.total())
 }
 
 #[cfg(test)]
@@ -72,13 +153,49 @@ mod tests {
 
     #[test]
     fn sample1() {
-        let result = count_stones(10, 1);
-        assert_eq!(result, 2);
+        let mut blinks = Blinks::new(&[10]);
+        blinks.blink().unwrap();
+        assert_eq!(blinks.total(), BigUint::from(2u32));
     }
 
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(55312));
+        assert_eq!(result, Ok(BigUint::from(55312u32)));
+    }
+
+    #[test]
+    fn test_distinct_values() {
+        let mut blinks = Blinks::new(&parse_stones(&advent_of_code::template::read_file(
+            "examples", DAY,
+        )));
+        // from the puzzle text: 125 17 -> 253000 1 7 -> 253 0 2024 14168
+        blinks.run(2).unwrap();
+        assert_eq!(blinks.distinct(), 4);
+        assert_eq!(blinks.distinct_seen(), 9);
+        assert_eq!(blinks.closed_at(), None);
+
+        // well past where a u64 total overflows
+        blinks.run(298).unwrap();
+        assert!(blinks.total().bits() > 64);
+        let closed_at = blinks.closed_at().unwrap();
+        let seen = blinks.distinct_seen();
+        assert!(closed_at < 300);
+        assert!(blinks.distinct() <= seen);
+        blinks.run(10).unwrap();
+        assert_eq!(blinks.distinct_seen(), seen);
+    }
+
+    #[test]
+    fn test_closed_at() {
+        // a lone 0 brings up a new value on each of its first 16 blinks, and none on the 17th
+        let mut blinks = Blinks::new(&[0]);
+        blinks.run(16).unwrap();
+        assert_eq!(blinks.closed_at(), None);
+        let seen = blinks.distinct_seen();
+        blinks.blink().unwrap();
+        assert_eq!(blinks.distinct_seen(), seen);
+        assert_eq!(blinks.closed_at(), Some(17));
+        assert!(describe(&blinks).ends_with("No new values appear from blink 17 on"));
     }
 }
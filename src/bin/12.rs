@@ -1,41 +1,232 @@
-#![allow(unused_imports)]
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::flag_once;
 use advent_of_code::Point;
 use hashbrown::{HashMap, HashSet};
 
-use rayon::prelude::*;
 advent_of_code::solution!(12);
 
-fn grid_contains(grid: &HashSet<Point>, point: Option<Point>) -> bool {
-    point.is_some() && grid.contains(&point.unwrap())
+/// A closed loop of grid corners, with a vertex only where the boundary turns.
+pub type Polygon = Vec<(u32, u32)>;
+
+/// A connected patch of one kind of plant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub plant: char,
+    /// Every cell, sorted by row then column.
+    pub cells: Vec<Point>,
+    /// The top left and bottom right cells of the bounding box, inclusive.
+    pub bounds: (Point, Point),
+    /// The outside boundary, clockwise on screen with y pointing down.
+    pub outer: Polygon,
+    /// One boundary per hole, running the other way round.
+    pub holes: Vec<Polygon>,
 }
 
-fn count_corners(grid: &HashSet<Point>) -> usize {
-    let mut edge_count = 0;
-    for &pt in grid {
-        let up = grid_contains(grid, pt.up(None));
-        let down = grid_contains(grid, pt.down(None));
-        let left = grid_contains(grid, pt.left(None));
-        let right = grid_contains(grid, pt.right(None));
-        let up_right = grid_contains(grid, pt.up_right(None, None));
-        let up_left = grid_contains(grid, pt.up_left(None, None));
-        let down_right = grid_contains(grid, pt.down_right(None, None));
-        let down_left = grid_contains(grid, pt.down_left(None, None));
-
-        if !up && !right || up && right && !up_right {
-            edge_count += 1;
+impl Region {
+    fn new(plant: char, cells: &HashSet<Point>) -> Self {
+        let mut cells: Vec<Point> = cells.iter().copied().collect();
+        cells.sort_by_key(|p| (p.y, p.x));
+        let min = Point::from((
+            cells.iter().map(|p| p.x).min().unwrap_or(0),
+            cells.iter().map(|p| p.y).min().unwrap_or(0),
+        ));
+        let max = Point::from((
+            cells.iter().map(|p| p.x).max().unwrap_or(0),
+            cells.iter().map(|p| p.y).max().unwrap_or(0),
+        ));
+
+        let (mut outer, mut holes) = (vec![], vec![]);
+        for polygon in trace_boundaries(&cells) {
+            // the only loop running clockwise is the outside one
+            if signed_area(&polygon) > 0 {
+                outer = polygon;
+            } else {
+                holes.push(polygon);
+            }
         }
-        if !up && !left || up && left && !up_left {
-            edge_count += 1;
+
+        Self {
+            plant,
+            cells,
+            bounds: (min, max),
+            outer,
+            holes,
+        }
+    }
+
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn boundaries(&self) -> impl Iterator<Item = &Polygon> {
+        std::iter::once(&self.outer).chain(&self.holes)
+    }
+
+    /// Length of fence around the outside and every hole.
+    pub fn perimeter(&self) -> usize {
+        self.boundaries()
+            .map(|polygon| {
+                polygon
+                    .iter()
+                    .zip(polygon.iter().cycle().skip(1))
+                    .map(|(a, b)| (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as usize)
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Straight runs of fence, one per polygon corner.
+    pub fn sides(&self) -> usize {
+        self.boundaries().map(Vec::len).sum()
+    }
+}
+
+// twice the polygon's area, positive when it runs clockwise on screen
+fn signed_area(polygon: &Polygon) -> i64 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64)
+        .sum()
+}
+
+// One unit of fence, running clockwise around its cell, with `outside` across it.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    from: (u32, u32),
+    to: (u32, u32),
+    outside: (i64, i64),
+}
+
+// Joins the unit fence edges into loops. Where a region touches itself diagonally, two
+// loops meet at a corner. The cells either side of that corner lie in different gaps of
+// the region, so following the edge with the same cell outside keeps one loop per gap.
+fn trace_boundaries(cells: &[Point]) -> Vec<Polygon> {
+    let in_region: HashSet<(i64, i64)> = cells.iter().map(|p| (p.x as i64, p.y as i64)).collect();
+    let mut edges = vec![];
+    for p in cells {
+        let (x, y) = (p.x, p.y);
+        let (ix, iy) = (x as i64, y as i64);
+        let sides = [
+            ((ix, iy - 1), (x, y), (x + 1, y)),
+            ((ix + 1, iy), (x + 1, y), (x + 1, y + 1)),
+            ((ix, iy + 1), (x + 1, y + 1), (x, y + 1)),
+            ((ix - 1, iy), (x, y + 1), (x, y)),
+        ];
+        for (outside, from, to) in sides {
+            if !in_region.contains(&outside) {
+                edges.push(Edge { from, to, outside });
+            }
         }
-        if !down && !right || down && right && !down_right {
-            edge_count += 1;
+    }
+
+    let mut outgoing: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (i, edge) in edges.iter().enumerate() {
+        outgoing.entry(edge.from).or_default().push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut polygons = vec![];
+    for start in 0..edges.len() {
+        if used[start] {
+            continue;
         }
-        if !down && !left || down && left && !down_left {
-            edge_count += 1;
+        used[start] = true;
+        let mut vertices = vec![edges[start].from];
+        let mut current = start;
+        loop {
+            let edge = edges[current];
+            let candidates: Vec<usize> = outgoing[&edge.to]
+                .iter()
+                .copied()
+                .filter(|&e| e == start || !used[e])
+                .collect();
+            let next = candidates
+                .iter()
+                .copied()
+                .find(|&e| edges[e].outside == edge.outside)
+                .unwrap_or(candidates[0]);
+            if next == start {
+                break;
+            }
+            used[next] = true;
+            vertices.push(edges[next].from);
+            current = next;
         }
+        polygons.push(corners(&vertices));
     }
+    polygons
+}
+
+// drops the vertices in the middle of straight runs
+fn corners(vertices: &[(u32, u32)]) -> Polygon {
+    let n = vertices.len();
+    (0..n)
+        .filter(|&i| {
+            let (prev, here, next) = (
+                vertices[(i + n - 1) % n],
+                vertices[i],
+                vertices[(i + 1) % n],
+            );
+            let straight =
+                (prev.0 == here.0 && here.0 == next.0) || (prev.1 == here.1 && here.1 == next.1);
+            !straight
+        })
+        .map(|i| vertices[i])
+        .collect()
+}
 
-    edge_count
+// a steady colour per plant, spread around the hue circle
+fn plant_colour(plant: char) -> String {
+    let hue = (plant as u32).wrapping_mul(137) % 360;
+    format!("hsl({hue}, 60%, 70%)")
+}
+
+/// Draws every region with its fence, holes cut out, labelled with its plant.
+pub fn to_svg(regions: &[Region], width: usize, height: usize) -> String {
+    const SCALE: u32 = 20;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">\n",
+        width as u32 * SCALE,
+        height as u32 * SCALE
+    );
+    for region in regions {
+        let mut path = String::new();
+        for polygon in region.boundaries() {
+            for (i, &(x, y)) in polygon.iter().enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                let _ = write!(path, "{command}{} {} ", x * SCALE, y * SCALE);
+            }
+            path.push_str("Z ");
+        }
+        let _ = writeln!(
+            svg,
+            "  <path d=\"{}\" fill=\"{}\" fill-rule=\"evenodd\" stroke=\"black\"><title>{}: area {}, perimeter {}, {} sides, {} holes</title></path>",
+            path.trim_end(),
+            plant_colour(region.plant),
+            region.plant,
+            region.area(),
+            region.perimeter(),
+            region.sides(),
+            region.holes.len()
+        );
+        // the first cell is always inside the region, so the label never lands in a hole
+        let label = region.cells[0];
+        let _ = writeln!(
+            svg,
+            "  <text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+            label.x * SCALE + SCALE / 2,
+            label.y * SCALE + SCALE / 2,
+            SCALE * 3 / 4,
+            region.plant
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
 }
 
 pub struct Garden {
@@ -105,8 +296,9 @@ impl Garden {
         neighbors
     }
 
-    pub fn find_areas(&self, is_part_2: bool) -> Vec<(usize, usize, char)> {
-        let mut area_perimeters: Vec<(usize, usize, char)> = Vec::new();
+    /// Every region, in the order their top left cells appear.
+    pub fn regions(&self) -> Vec<Region> {
+        let mut regions = Vec::new();
         let mut visited: HashMap<Point, bool> = HashMap::new();
 
         for (y, row) in self.grid.iter().enumerate() {
@@ -114,54 +306,93 @@ impl Garden {
                 let point = Point::from((x, y));
                 if !visited.contains_key(&point) {
                     let neighbors = self.find_neighbors(&point, ch, &mut visited);
-
-                    // find area
-                    let area = neighbors.len();
-
-                    // find perimeter
-                    let perimeter = if is_part_2 {
-                        count_corners(&neighbors)
-                    } else {
-                        neighbors
-                            .iter()
-                            .map(|pt| self.find_neighbor_count(pt, ch))
-                            .sum::<usize>()
-                    };
-
-                    area_perimeters.push((area, perimeter, *ch));
+                    regions.push(Region::new(*ch, &neighbors));
                 }
             }
         }
 
-        area_perimeters
+        regions
     }
 
-    pub fn fence_pricing(&self, is_part_2: bool) -> Option<usize> {
-        Some(
-            self.find_areas(is_part_2)
-                .iter()
-                .map(|(a, p, _)| a * p)
-                .sum(),
-        )
+    pub fn fence_pricing(&self, is_part_2: bool) -> usize {
+        self.regions()
+            .iter()
+            .map(|region| {
+                let fence = if is_part_2 {
+                    region.sides()
+                } else {
+                    region.perimeter()
+                };
+                region.area() * fence
+            })
+            .sum()
     }
 }
 
+// `--svg` writes the regions to target/12.svg, once per run
+fn export_svg(garden: &Garden) -> Result<(), SolutionError> {
+    if !flag_once("--svg") {
+        return Ok(());
+    }
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/12.svg");
+    let svg = to_svg(&garden.regions(), garden.width, garden.height);
+    fs::write(&path, svg)
+        .map_err(|e| SolutionError::new(format!("could not write {}: {e}", path.display())))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
 pub fn part_one(input: &str) -> Option<usize> {
     let garden = Garden::parse(input);
 
-    garden.fence_pricing(false)
+    Some(garden.fence_pricing(false))
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
     let garden = Garden::parse(input);
+    export_svg(&garden)?;
 
-    garden.fence_pricing(true)
+    Ok(garden.fence_pricing(true))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn grid_contains(grid: &HashSet<Point>, point: Option<Point>) -> bool {
+        point.is_some() && grid.contains(&point.unwrap())
+    }
+
+    fn count_corners(grid: &HashSet<Point>) -> usize {
+        let mut edge_count = 0;
+        for &pt in grid {
+            let up = grid_contains(grid, pt.up(None));
+            let down = grid_contains(grid, pt.down(None));
+            let left = grid_contains(grid, pt.left(None));
+            let right = grid_contains(grid, pt.right(None));
+            let up_right = grid_contains(grid, pt.up_right(None, None));
+            let up_left = grid_contains(grid, pt.up_left(None, None));
+            let down_right = grid_contains(grid, pt.down_right(None, None));
+            let down_left = grid_contains(grid, pt.down_left(None, None));
+
+            if !up && !right || up && right && !up_right {
+                edge_count += 1;
+            }
+            if !up && !left || up && left && !up_left {
+                edge_count += 1;
+            }
+            if !down && !right || down && right && !down_right {
+                edge_count += 1;
+            }
+            if !down && !left || down && left && !down_left {
+                edge_count += 1;
+            }
+        }
+
+        edge_count
+    }
+
     #[test]
     fn test_count_corners() {
         let mut points: HashSet<Point> = HashSet::new();
@@ -191,13 +422,56 @@ mod tests {
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(1206));
+        assert_eq!(result, Ok(1206));
     }
     #[test]
     fn test_part_two_2() {
         let result = part_two(&advent_of_code::template::read_file_part(
             "examples", DAY, 2,
         ));
-        assert_eq!(result, Some(368));
+        assert_eq!(result, Ok(368));
+    }
+
+    #[test]
+    fn test_sides_match_corners() {
+        for part in [None, Some(2)] {
+            let input = match part {
+                Some(part) => advent_of_code::template::read_file_part("examples", DAY, part),
+                None => advent_of_code::template::read_file("examples", DAY),
+            };
+            for region in Garden::parse(&input).regions() {
+                let cells: HashSet<Point> = region.cells.iter().copied().collect();
+                assert_eq!(region.sides(), count_corners(&cells), "{region:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_region_holes() {
+        // an O region with two X holes
+        let garden = Garden::parse("OOOOO\nOXOXO\nOOOOO\n");
+        let regions = garden.regions();
+        let ring = &regions[0];
+        assert_eq!(ring.area(), 13);
+        assert_eq!(ring.outer, vec![(0, 0), (5, 0), (5, 3), (0, 3)]);
+        assert_eq!(ring.holes.len(), 2);
+        assert_eq!(ring.holes[0], vec![(2, 1), (1, 1), (1, 2), (2, 2)]);
+        assert_eq!(ring.perimeter(), 16 + 8);
+        assert_eq!(
+            ring.bounds,
+            (Point::from((0u32, 0)), Point::from((4u32, 2)))
+        );
+
+        let garden = Garden::parse(&advent_of_code::template::read_file_part(
+            "examples", DAY, 2,
+        ));
+        let regions = garden.regions();
+        // the A region wraps round two B regions that touch at one corner
+        assert_eq!(regions[0].holes.len(), 2);
+        assert_eq!(regions[0].sides(), 12);
+
+        let svg = to_svg(&regions, garden.width, garden.height);
+        assert_eq!(svg.matches("<path").count(), regions.len());
+        assert!(svg.contains("A: area 28, perimeter 40, 12 sides, 2 holes"));
     }
 }
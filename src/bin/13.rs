@@ -1,5 +1,8 @@
 advent_of_code::solution!(13);
 
+use advent_of_code::math::min_cost_combination;
+use advent_of_code::parse::finish;
+use advent_of_code::template::SolutionError;
 use aoc_mine::Coord;
 
 use nom::{
@@ -32,64 +35,35 @@ fn parse_input(input: &str) -> IResult<&str, Vec<(CoordI, CoordI, CoordI)>> {
 }
 
 // originally solved part 1 with an iterative approach.. that certainly
-// didn't work for part 2. Linear algebra to the rescue!
+// didn't work for part 2. Linear algebra to the rescue! The library solver
+// also covers collinear buttons, where the cheapest mix of presses wins.
 fn solve_machine(a: Coord<i64>, b: Coord<i64>, p: Coord<i64>) -> Option<i64> {
-    let (ax, ay) = (a.x(), a.y());
-    let (bx, by) = (b.x(), b.y());
-    let (px, py) = (p.x(), p.y());
+    let (a_presses, b_presses) =
+        min_cost_combination((a.x(), a.y()), (b.x(), b.y()), (p.x(), p.y()), (3, 1))?;
 
-    let denominator = bx * ay - ax * by;
-    if denominator == 0 {
-        // can't divide by zero, so no valid solution
-        return None;
-    }
-
-    let numerator = bx * py - by * px;
-    if numerator % denominator != 0 {
-        // equations don't align to discrete button presses
-        // (can't press a button 0.25)
-        return None;
-    }
-
-    let x = numerator / denominator;
-    let y = (px - ax * x) / bx;
-
-    if ay * x + by * y != py {
-        // button presses for x solution don't line up on the y-axis
-        return None;
-    }
-
-    // x costs 3 tokens
-    Some(x * 3 + y)
+    // a costs 3 tokens
+    Some(a_presses * 3 + b_presses)
 }
 
-pub fn part_one(input: &str) -> Option<i64> {
-    Some(
-        parse_input(input)
-            .unwrap()
-            .1
-            .into_iter()
-            .filter_map(|(a, b, p)| solve_machine(a, b, p))
-            .sum(),
-    )
+pub fn part_one(input: &str) -> Result<i64, SolutionError> {
+    Ok(finish(input, parse_input)?
+        .into_iter()
+        .filter_map(|(a, b, p)| solve_machine(a, b, p))
+        .sum())
 }
 
-pub fn part_two(input: &str) -> Option<i64> {
-    Some(
-        parse_input(input)
-            .unwrap()
-            .1
-            .into_iter()
-            .map(|(a, b, p)| {
-                let (mut px, mut py) = p.into();
-                px += PART_2_OFFSET;
-                py += PART_2_OFFSET;
-
-                (a, b, (px, py).into())
-            })
-            .filter_map(|(a, b, p)| solve_machine(a, b, p))
-            .sum(),
-    )
+pub fn part_two(input: &str) -> Result<i64, SolutionError> {
+    Ok(finish(input, parse_input)?
+        .into_iter()
+        .map(|(a, b, p)| {
+            let (mut px, mut py) = p.into();
+            px += PART_2_OFFSET;
+            py += PART_2_OFFSET;
+
+            (a, b, (px, py).into())
+        })
+        .filter_map(|(a, b, p)| solve_machine(a, b, p))
+        .sum())
 }
 
 #[cfg(test)]
@@ -99,12 +73,12 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(480));
+        assert_eq!(result, Ok(480));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(875318608908));
+        assert_eq!(result, Ok(875318608908));
     }
 }
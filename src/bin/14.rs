@@ -1,55 +1,122 @@
 advent_of_code::solution!(14);
 
 use aoc_mine::Coord;
-use rayon::prelude::*;
-use std::{
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
-    thread::available_parallelism,
-};
+use std::fmt::Display;
+use std::fs;
 
+use advent_of_code::math::crt;
+use advent_of_code::parse::{ParseError, finish, key_pair, lines};
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::{Palette, Visualize, Visualizer, flag_once, frame_dir, render_pbm};
 use hashbrown::HashMap;
-use nom::{
-    IResult,
-    bytes::complete::tag,
-    character::complete::{char, i64, multispace0, multispace1, u64},
-    combinator::map,
-    multi::separated_list1,
-    sequence::{preceded, separated_pair, tuple},
-};
+use nom::{IResult, character::complete::space1, combinator::map, sequence::separated_pair};
 
 type RobotPosition = Coord<usize>;
 type RobotVelocity = Coord<i64>;
 type RobotPositionsAndVelocity = (RobotPosition, RobotVelocity);
 
 fn parse_line(input: &str) -> IResult<&str, RobotPositionsAndVelocity> {
-    let parse_usize_pair = map(separated_pair(u64, char(','), u64), |(a, b)| {
-        (a as usize, b as usize)
-    });
-    let parse_i64_pair = separated_pair(i64, char(','), i64);
+    map(
+        separated_pair(key_pair::<usize>("p"), space1, key_pair::<i64>("v")),
+        |(p, v)| (p.into(), v.into()),
+    )(input)
+}
 
-    let parse_p = preceded(tag("p="), parse_usize_pair);
-    let parse_v = preceded(tag("v="), parse_i64_pair);
+fn parse_input(input: &str) -> Result<Vec<RobotPositionsAndVelocity>, ParseError> {
+    finish(input, lines(parse_line))
+}
 
-    map(tuple((parse_p, multispace1, parse_v)), |(p, _, v)| {
-        (p.into(), v.into())
-    })(input)
+/// How many robots stand on each tile after some number of steps.
+pub struct Floor {
+    counts: Vec<u8>,
+    width: usize,
+    height: usize,
 }
 
-fn parse_input(input: &str) -> IResult<&str, Vec<RobotPositionsAndVelocity>> {
-    separated_list1(multispace0, parse_line)(input)
+impl Floor {
+    fn after(
+        robots: &[RobotPositionsAndVelocity],
+        steps: i64,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let mut counts = vec![0u8; width * height];
+        for robot in robots {
+            let p = step_robot(robot, steps, width, height);
+            counts[p.y() * width + p.x()] = counts[p.y() * width + p.x()].saturating_add(1);
+        }
+        Self {
+            counts,
+            width,
+            height,
+        }
+    }
+
+    /// `#` for every tile with a robot on it, `.` otherwise.
+    pub fn to_text(&self) -> String {
+        self.counts
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|&c| if c > 0 { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
+impl Visualize for Floor {
+    type Cell = u8;
+
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> u8 {
+        self.counts[y * self.width + x].min(3)
+    }
+}
+
+// draws the floor after each number of steps in `steps`, if `--visualize` was passed
+#[track_caller]
+fn visualize_steps(
+    robots: &[RobotPositionsAndVelocity],
+    steps: impl IntoIterator<Item = i64>,
+    width: usize,
+    height: usize,
+) {
+    let palette = Palette::new('.', [10, 10, 30])
+        .with(1, '1', [60, 200, 90])
+        .with(2, '2', [160, 230, 80])
+        .with(3, '+', [250, 250, 120]);
+    let Some(mut visualizer) = Visualizer::from_args(DAY, palette) else {
+        return;
+    };
+
+    let mut last = None;
+    for step in steps {
+        let floor = Floor::after(robots, step, width, height);
+        visualizer.frame(&floor);
+        last = Some(floor);
+    }
+    if let Some(floor) = last {
+        visualizer.finish(&floor);
+    }
+}
+
+pub fn part_one(input: &str) -> Result<u32, SolutionError> {
     let steps: i64 = 100;
     let is_test = input.len() < 200;
     let width = if is_test { 11 } else { 101 };
     let height = if is_test { 7 } else { 103 };
-    let robots = parse_input(input).unwrap().1;
+    let robots = parse_input(input)?;
 
     let updated_robots: Vec<RobotPosition> = robots
         .iter()
         .map(|robot| step_robot(robot, steps, width, height))
         .collect();
+    visualize_steps(&robots, 0..=steps, width, height);
 
     let half_x = width / 2;
     let half_y = height / 2;
@@ -73,7 +140,7 @@ pub fn part_one(input: &str) -> Option<u32> {
             *quadrants.entry(q).or_insert(0) += 1;
         });
 
-    Some(quadrants.iter().map(|(_, v)| v).product())
+    Ok(quadrants.iter().map(|(_, v)| v).product())
 }
 
 // steps the robots immediately to their final offset, then finds the remainder
@@ -90,66 +157,117 @@ fn step_robot(
     (px, py).into()
 }
 
-fn find_lines(points: &mut [RobotPosition]) -> bool {
-    // Sort points by (x, y)
-    points.sort_unstable();
+// n^2 times the variance, which is enough to compare spreads of the same number of values
+fn spread(values: impl Iterator<Item = i64>) -> i64 {
+    let (n, sum, squares) = values.fold((0, 0, 0), |(n, sum, squares), v| {
+        (n + 1, sum + v, squares + v * v)
+    });
+    n * squares - sum * sum
+}
+
+// how many standard deviations below the other steps the tightest one has to be, which
+// chance alone doesn't get near for a full input's hundreds of robots
+const OUTLIER: f64 = 5.0;
+
+// the step in `0..period` at which one axis is most tightly bunched, if it stands out from
+// every other step
+fn tightest_step(period: usize, axis: impl Fn(i64) -> Vec<i64>) -> Option<usize> {
+    let spreads: Vec<f64> = (0..period)
+        .map(|t| spread(axis(t as i64).into_iter()) as f64)
+        .collect();
+    let (step, &tightest) = spreads
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))?;
+
+    let others: Vec<f64> = (0..period)
+        .filter(|&t| t != step)
+        .map(|t| spreads[t])
+        .collect();
+    let n = others.len() as f64;
+    let mean = others.iter().sum::<f64>() / n;
+    let deviation = (others.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n).sqrt();
+    (tightest < mean - OUTLIER * deviation).then_some(step)
+}
+
+/// Finds the step at which the robots cluster into a picture, if they ever do.
+///
+/// x positions repeat every `width` steps and y positions every `height`, so the step with the
+/// least x variance in one x period and the least y variance in one y period pin the picture
+/// down, and the Chinese remainder theorem combines them. Without a picture neither stands
+/// out from the other steps, and there is no tree.
+pub fn find_tree(
+    robots: &[RobotPositionsAndVelocity],
+    width: usize,
+    height: usize,
+) -> Option<usize> {
+    let tx = tightest_step(width, |t| {
+        robots
+            .iter()
+            .map(|(p, v)| (p.x() as i64 + v.x() * t).rem_euclid(width as i64))
+            .collect()
+    })?;
+    let ty = tightest_step(height, |t| {
+        robots
+            .iter()
+            .map(|(p, v)| (p.y() as i64 + v.y() * t).rem_euclid(height as i64))
+            .collect()
+    })?;
 
-    let mut last_x = None;
-    let mut last_y = None;
-    let mut count = 0;
+    let (step, _) = crt(&[(tx as i64, width as i64), (ty as i64, height as i64)])?;
+    Some(step as usize)
+}
 
-    let quarter_idx = points.len() / 4;
-    let three_quarter_idx = quarter_idx * 3;
+/// The step the tree appears at, and the tree itself when `--picture` was passed, or that
+/// the robots never form one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree {
+    Found {
+        steps: usize,
+        picture: Option<String>,
+    },
+    NotFound,
+}
 
-    for &mut p in &mut points[quarter_idx..three_quarter_idx] {
-        let x = p.x();
-        let y = p.y();
-        if Some(x) == last_x && Some(y) == last_y.map(|ly| ly + 1) {
-            count += 1;
-            if count >= 10 {
-                return true;
+impl Display for Tree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tree::Found { steps, picture } => {
+                write!(f, "{steps}")?;
+                if let Some(picture) = picture {
+                    write!(f, "\n{picture}")?;
+                }
+                Ok(())
             }
-        } else {
-            count = 1;
+            Tree::NotFound => write!(f, "no tree found"),
         }
-        last_x = Some(x);
-        last_y = Some(y);
     }
-
-    false
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
+pub fn part_two(input: &str) -> Result<Tree, SolutionError> {
     let is_test = input.len() < 200;
     let width = if is_test { 11 } else { 101 };
     let height = if is_test { 7 } else { 103 };
-    let robots = parse_input(input).unwrap().1;
-
-    let thread_count = available_parallelism().unwrap().get().max(16);
-    let found = AtomicBool::new(false);
-    let steps = AtomicUsize::new(0);
-
-    (0..thread_count).into_par_iter().for_each(|offset| {
-        let mut i = 0;
-        loop {
-            let check = i * thread_count + offset;
-            if check > 10000 || found.load(Ordering::Relaxed) {
-                return;
-            }
-            let mut updated_robots: Vec<RobotPosition> = robots
-                .iter()
-                .map(|robot| step_robot(robot, check as i64, width, height))
-                .collect();
-            if find_lines(&mut updated_robots) {
-                found.store(true, Ordering::Relaxed);
-                steps.store(check, Ordering::Relaxed);
-            } else {
-                i += 1;
-            }
-        }
-    });
+    let robots = parse_input(input)?;
 
-    Some(steps.load(Ordering::Relaxed))
+    let Some(steps) = find_tree(&robots, width, height) else {
+        return Ok(Tree::NotFound);
+    };
+    visualize_steps(&robots, 0..=steps as i64, width, height);
+
+    // `--picture` saves the tree as a PBM image and includes it in the answer
+    let mut picture = None;
+    if flag_once("--picture") {
+        let floor = Floor::after(&robots, steps as i64, width, height);
+        let dir = frame_dir(DAY);
+        let path = dir.join("tree.pbm");
+        fs::create_dir_all(&dir)
+            .and_then(|()| fs::write(&path, render_pbm(&floor, |count| count > 0)))
+            .map_err(|e| SolutionError::new(format!("could not write {}: {e}", path.display())))?;
+        picture = Some(floor.to_text());
+    }
+
+    Ok(Tree::Found { steps, picture })
 }
 
 #[cfg(test)]
@@ -159,12 +277,58 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(12));
+        assert_eq!(result, Ok(12));
     }
 
     #[test]
     fn test_part_two() {
+        // the example's robots never bunch up into a picture
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(0));
+        assert_eq!(result, Ok(Tree::NotFound));
+    }
+
+    #[test]
+    fn test_find_tree() {
+        // scatter robots so that they all land in a small square at step 4321
+        let (width, height, target) = (101, 103, 4321);
+        let mut seed = 12345u64;
+        let mut next = |limit: i64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) as i64).rem_euclid(limit)
+        };
+        let robots: Vec<RobotPositionsAndVelocity> = (0..500)
+            .map(|_| {
+                let at = (40 + next(15), 50 + next(20));
+                let v = (next(201) - 100, next(201) - 100);
+                let p = (
+                    (at.0 - v.0 * target).rem_euclid(width),
+                    (at.1 - v.1 * target).rem_euclid(height),
+                );
+                (Coord::new(p.0 as usize, p.1 as usize), Coord::new(v.0, v.1))
+            })
+            .collect();
+
+        assert_eq!(
+            find_tree(&robots, width as usize, height as usize),
+            Some(target as usize)
+        );
+
+        let floor = Floor::after(&robots, target, width as usize, height as usize);
+        let text = floor.to_text();
+        assert_eq!(text.lines().count(), 103);
+        assert!(text.lines().nth(55).unwrap()[40..55].contains('#'));
+        assert!(!text.lines().next().unwrap().contains('#'));
+    }
+
+    #[test]
+    fn test_tree_display() {
+        let tree = Tree::Found {
+            steps: 7,
+            picture: Some("#.\n.#".to_string()),
+        };
+        assert_eq!(tree.to_string(), "7\n#.\n.#");
+        assert_eq!(Tree::NotFound.to_string(), "no tree found");
     }
 }
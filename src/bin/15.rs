@@ -1,10 +1,17 @@
 use aoc_mine::{Coord, Grid, LinearGrid};
 
-advent_of_code::solution!(15);
+advent_of_code::solution!(15, before: play_if_asked);
 
+use std::io::{self, IsTerminal, Write};
+
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::{render_ansi, Palette, Visualize, Visualizer};
 use advent_of_code::Direction;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BlockType {
     Wall,
     Box,
@@ -14,6 +21,30 @@ pub enum BlockType {
     Robot,
 }
 
+/// Contents shifted from one cell to the next, as `(from, to, contents)`.
+pub type Shift = (Coord<usize>, Coord<usize>, BlockType);
+
+/// One robot move and the cells it shifted, enough to undo it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub direction: Direction,
+    /// Where the robot stood before the move.
+    pub robot: Coord<usize>,
+    /// Every shift the move made, in order.
+    pub moves: Vec<Shift>,
+}
+
+impl Step {
+    /// The box cells this step pushed, at their new positions.
+    pub fn pushed_boxes(&self) -> Vec<Coord<usize>> {
+        self.moves
+            .iter()
+            .filter(|(_, _, contents)| matches!(contents, BlockType::Box | BlockType::BoxRight))
+            .map(|&(_, to, _)| to)
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Warehouse {
     pub grid: LinearGrid<usize, BlockType>,
@@ -21,90 +52,99 @@ pub struct Warehouse {
     pub width: usize,
     pub height: usize,
     pub directions: Vec<Direction>,
+    /// Collects the cells moved during a [`Warehouse::step`].
+    pub journal: Option<Vec<Shift>>,
 }
 
 impl Warehouse {
-    pub fn parse_input(input: &str, part_2: bool) -> Self {
-        let (graph, directions) = input.split_once("\n\n").unwrap();
+    pub fn parse_input(input: &str, part_2: bool) -> Result<Self, SolutionError> {
+        let (graph, directions) = input
+            .split_once("\n\n")
+            .ok_or_else(|| SolutionError::new("expected a blank line before the directions"))?;
         let mut robot_position = None;
-        let mut width = input.lines().next().unwrap().len();
+        let mut width = graph.lines().next().map_or(0, str::len);
         if part_2 {
             width *= 2;
         }
-        let height = graph.lines().collect::<Vec<_>>().len();
+        let height = graph.lines().count();
         let mut grid: LinearGrid<usize, BlockType> =
             LinearGrid::new(width, height, BlockType::Open);
-        if !part_2 {
-            graph.lines().enumerate().for_each(|(y, line)| {
-                line.chars().enumerate().for_each(|(x, ch)| {
-                    let mut block_type = match ch {
-                        '#' => BlockType::Wall,
-                        '@' => BlockType::Robot,
-                        '.' => BlockType::Open,
-                        'O' => BlockType::Box,
-                        _ => unreachable!("Should not have received {ch}"),
-                    };
-                    if block_type == BlockType::Robot {
-                        robot_position = Some((x, y).into());
-                        block_type = BlockType::Open;
+        for (y, line) in graph.lines().enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                let (block_type_l, block_type_r) = match ch {
+                    '#' => (BlockType::Wall, BlockType::Wall),
+                    '@' => (BlockType::Robot, BlockType::Open),
+                    '.' => (BlockType::Open, BlockType::Open),
+                    'O' => (BlockType::Box, BlockType::BoxRight),
+                    _ => {
+                        return Err(SolutionError::at_line(
+                            y + 1,
+                            x + 1,
+                            format!("unexpected warehouse tile {ch:?}"),
+                        ))
                     }
+                };
 
-                    let _ = grid.insert(Coord::new(x, y), block_type);
-                });
-            });
-        } else {
-            graph.lines().enumerate().for_each(|(y, line)| {
-                line.chars().enumerate().for_each(|(x, ch)| {
-                    let mut block_type_l = match ch {
-                        '#' => BlockType::Wall,
-                        '@' => BlockType::Robot,
-                        '.' => BlockType::Open,
-                        'O' => BlockType::Box,
-                        _ => unreachable!("Should not have received {ch}"),
-                    };
-                    let block_type_r = match ch {
-                        '#' => BlockType::Wall,
-                        '@' => BlockType::Open,
-                        '.' => BlockType::Open,
-                        'O' => BlockType::BoxRight,
-                        _ => unreachable!("Should not have received {ch}"),
-                    };
+                if part_2 {
                     if block_type_l == BlockType::Robot {
                         robot_position = Some((x * 2, y).into());
-                        block_type_l = BlockType::Open;
                     }
-
+                    let block_type_l = match block_type_l {
+                        BlockType::Robot => BlockType::Open,
+                        b => b,
+                    };
                     let _ = grid.insert(Coord::new(x * 2, y), block_type_l);
                     let _ = grid.insert(Coord::new(x * 2 + 1, y), block_type_r);
-                });
-            });
+                } else {
+                    let mut block_type = block_type_l;
+                    if block_type == BlockType::Robot {
+                        robot_position = Some((x, y).into());
+                        block_type = BlockType::Open;
+                    }
+                    let _ = grid.insert(Coord::new(x, y), block_type);
+                }
+            }
         }
 
-        let directions: Vec<Direction> = directions
-            .replace('\n', "")
-            .chars()
-            .map(|ch| match ch {
-                '^' => Direction::Up,
-                'v' => Direction::Down,
-                '>' => Direction::Right,
-                '<' => Direction::Left,
-                _ => unreachable!("Invalid character in directions: {ch}"),
-            })
-            .collect();
+        // directions start two lines below the warehouse map
+        let first_direction_line = height + 2;
+        let mut parsed_directions = Vec::with_capacity(directions.len());
+        for (y, line) in directions.lines().enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                parsed_directions.push(match ch {
+                    '^' => Direction::Up,
+                    'v' => Direction::Down,
+                    '>' => Direction::Right,
+                    '<' => Direction::Left,
+                    _ => {
+                        return Err(SolutionError::at_line(
+                            first_direction_line + y,
+                            x + 1,
+                            format!("invalid character in directions: {ch:?}"),
+                        ))
+                    }
+                });
+            }
+        }
 
-        Self {
+        Ok(Self {
             grid,
-            robot_position: robot_position.expect("did not find robot position"),
+            robot_position: robot_position
+                .ok_or_else(|| SolutionError::new("did not find robot position"))?,
             width,
             height,
-            directions,
-        }
+            directions: parsed_directions,
+            journal: None,
+        })
     }
 
     pub fn move_unchecked(&mut self, old_position: &Coord<usize>, new_position: &Coord<usize>) {
         let contents = *self.grid.get(old_position).unwrap();
         let _ = self.grid.insert(*old_position, BlockType::Open);
         let _ = self.grid.insert(*new_position, contents);
+        if let Some(journal) = &mut self.journal {
+            journal.push((*old_position, *new_position, contents));
+        }
     }
 
     pub fn attempt_move(
@@ -224,16 +264,53 @@ impl Warehouse {
     }
 
     pub fn follow_robot_directions(&mut self) {
-        for direction in self.directions.clone() {
-            let robot_position = self.robot_position;
-            self.attempt_move(&robot_position, direction, true);
-        }
+        self.follow_robot_directions_with(false, |_| {});
     }
     pub fn follow_robot_directions_part2(&mut self) {
+        self.follow_robot_directions_with(true, |_| {});
+    }
+
+    /// Follows every direction, calling `after_step` with the warehouse after each move.
+    pub fn follow_robot_directions_with(
+        &mut self,
+        part_2: bool,
+        mut after_step: impl FnMut(&Self),
+    ) {
         for direction in self.directions.clone() {
-            let robot_position = self.robot_position;
-            self.attempt_move_part2(&robot_position, direction, true, false);
+            self.apply(direction, part_2);
+            after_step(self);
+        }
+    }
+
+    fn apply(&mut self, direction: Direction, part_2: bool) -> bool {
+        let robot_position = self.robot_position;
+        if part_2 {
+            self.attempt_move_part2(&robot_position, direction, true, false)
+        } else {
+            self.attempt_move(&robot_position, direction, true)
+        }
+    }
+
+    /// Moves the robot once, returning what the move changed so it can be undone.
+    pub fn step(&mut self, direction: Direction, part_2: bool) -> Step {
+        let robot = self.robot_position;
+        self.journal = Some(vec![]);
+        self.apply(direction, part_2);
+        Step {
+            direction,
+            robot,
+            moves: self.journal.take().unwrap_or_default(),
+        }
+    }
+
+    /// Reverts `step`, which must be the last step taken.
+    pub fn undo(&mut self, step: &Step) {
+        // every move was into an open cell, so replaying them backwards restores the grid
+        for &(from, to, contents) in step.moves.iter().rev() {
+            let _ = self.grid.insert(to, BlockType::Open);
+            let _ = self.grid.insert(from, contents);
         }
+        self.robot_position = step.robot;
     }
 
     pub fn coordinate_summation(&self) -> usize {
@@ -273,24 +350,279 @@ impl Warehouse {
     }
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut warehouse = Warehouse::parse_input(input, false);
+impl Visualize for Warehouse {
+    type Cell = BlockType;
 
-    warehouse.follow_robot_directions();
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> BlockType {
+        let p = Coord::new(x, y);
+        if p == self.robot_position {
+            return BlockType::Robot;
+        }
+        *self.grid.get(&p).unwrap_or(&BlockType::Open)
+    }
+}
+
+fn palette(part_2: bool) -> Palette<BlockType> {
+    let box_glyph = if part_2 { '[' } else { 'O' };
+    Palette::new('.', [30, 30, 30])
+        .with(BlockType::Wall, '#', [120, 120, 130])
+        .with(BlockType::Box, box_glyph, [210, 150, 70])
+        .with(BlockType::BoxRight, ']', [180, 120, 50])
+        .with(BlockType::Robot, '@', [80, 220, 120])
+}
+
+// follows the directions, drawing each step if `--visualize` was passed
+#[track_caller]
+fn run(warehouse: &mut Warehouse, part_2: bool) {
+    match Visualizer::from_args(DAY, palette(part_2)) {
+        Some(mut visualizer) => {
+            visualizer.frame(&*warehouse);
+            warehouse.follow_robot_directions_with(part_2, |w| visualizer.frame(w));
+            visualizer.finish(&*warehouse);
+        }
+        None if part_2 => warehouse.follow_robot_directions_part2(),
+        None => warehouse.follow_robot_directions(),
+    }
+}
+
+/// What a key press asks the player to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    /// Take the next `n` moves from the list.
+    Next(usize),
+    /// Take back the last `n` moves, listed or not.
+    Undo(usize),
+    /// Take every remaining move from the list.
+    ToEnd,
+    /// Move the robot off-script.
+    Move(Direction),
+    Quit,
+}
+
+impl Command {
+    fn from_key(key: KeyEvent) -> Option<Self> {
+        let command = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Self::Quit,
+            KeyCode::Up => Self::Move(Direction::Up),
+            KeyCode::Down => Self::Move(Direction::Down),
+            KeyCode::Left => Self::Move(Direction::Left),
+            KeyCode::Right => Self::Move(Direction::Right),
+            KeyCode::Char('n' | ' ') => Self::Next(1),
+            KeyCode::Char('N') => Self::Next(100),
+            KeyCode::Char('u') | KeyCode::Backspace => Self::Undo(1),
+            KeyCode::Char('U') => Self::Undo(100),
+            KeyCode::Char('e') => Self::ToEnd,
+            KeyCode::Char('q') | KeyCode::Esc => Self::Quit,
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+const KEY_HELP: &str =
+    "arrows: move robot  n/space: next  N: next 100  u/backspace: undo  U: undo 100  e: end  q: quit";
+
+/// A warehouse being stepped through by hand, with every move kept for undo.
+#[derive(Debug)]
+struct Player {
+    warehouse: Warehouse,
+    part_2: bool,
+    // each step, and whether it came from the direction list
+    history: Vec<(Step, bool)>,
+    // index of the next listed direction
+    next: usize,
+    // box cells pushed by the last move, to highlight
+    moved: Vec<Coord<usize>>,
+}
+
+impl Player {
+    fn new(warehouse: Warehouse, part_2: bool) -> Self {
+        Self {
+            warehouse,
+            part_2,
+            history: vec![],
+            next: 0,
+            moved: vec![],
+        }
+    }
+
+    fn take(&mut self, direction: Direction, listed: bool) {
+        let step = self.warehouse.step(direction, self.part_2);
+        self.moved = step.pushed_boxes();
+        self.history.push((step, listed));
+        if listed {
+            self.next += 1;
+        }
+    }
+
+    fn apply(&mut self, command: Command) {
+        match command {
+            Command::Next(n) => {
+                for _ in 0..n {
+                    let Some(&direction) = self.warehouse.directions.get(self.next) else {
+                        break;
+                    };
+                    self.take(direction, true);
+                }
+            }
+            Command::ToEnd => self.apply(Command::Next(self.warehouse.directions.len())),
+            Command::Move(direction) => self.take(direction, false),
+            Command::Undo(n) => {
+                for _ in 0..n {
+                    let Some((step, listed)) = self.history.pop() else {
+                        break;
+                    };
+                    self.warehouse.undo(&step);
+                    if listed {
+                        self.next -= 1;
+                    }
+                }
+                self.moved.clear();
+            }
+            Command::Quit => {}
+        }
+    }
+
+    fn status(&self) -> String {
+        let next = self
+            .warehouse
+            .directions
+            .get(self.next)
+            .map_or('-', |&d| direction_glyph(d));
+        let off_script = self.history.iter().filter(|(_, listed)| !listed).count();
+        format!(
+            "part {}  move {}/{}  next {next}  off-script {off_script}  GPS sum {}  pushed {} box cells",
+            if self.part_2 { 2 } else { 1 },
+            self.next,
+            self.warehouse.directions.len(),
+            self.warehouse.coordinate_summation(),
+            self.moved.len(),
+        )
+    }
+}
+
+fn direction_glyph(direction: Direction) -> char {
+    match direction {
+        Direction::Up => '^',
+        Direction::Down => 'v',
+        Direction::Left => '<',
+        Direction::Right => '>',
+    }
+}
+
+impl Visualize for Player {
+    // a block, and whether the last move pushed it
+    type Cell = (BlockType, bool);
+
+    fn size(&self) -> (usize, usize) {
+        self.warehouse.size()
+    }
+
+    fn cell(&self, x: usize, y: usize) -> (BlockType, bool) {
+        let pushed = self.moved.contains(&Coord::new(x, y));
+        (self.warehouse.cell(x, y), pushed)
+    }
+}
+
+fn play_palette(part_2: bool) -> Palette<(BlockType, bool)> {
+    let box_glyph = if part_2 { '[' } else { 'O' };
+    Palette::new('.', [60, 60, 60])
+        .with((BlockType::Wall, false), '#', [120, 120, 130])
+        .with((BlockType::Box, false), box_glyph, [210, 150, 70])
+        .with((BlockType::BoxRight, false), ']', [210, 150, 70])
+        .with((BlockType::Box, true), box_glyph, [255, 80, 200])
+        .with((BlockType::BoxRight, true), ']', [255, 80, 200])
+        .with((BlockType::Robot, false), '@', [80, 220, 120])
+}
+
+// raw mode on an alternate screen for as long as this lives
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// `--play` steps through the warehouse in the terminal, drawn on stderr
+fn play(warehouse: &Warehouse, part_2: bool) -> io::Result<()> {
+    let mut player = Player::new(warehouse.clone(), part_2);
+    let palette = play_palette(part_2);
+    let _terminal = RawTerminal::enter()?;
+
+    loop {
+        let frame = render_ansi(&player, &palette).replace('\n', "\r\n");
+        let mut stderr = io::stderr();
+        queue!(stderr, cursor::MoveTo(0, 0), Clear(ClearType::All))?;
+        write!(stderr, "{frame}\r\n{}\r\n{KEY_HELP}", player.status())?;
+        stderr.flush()?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match Command::from_key(key) {
+            Some(Command::Quit) => return Ok(()),
+            Some(command) => player.apply(command),
+            None => {}
+        }
+    }
+}
+
+// plays the first warehouse and then the wide one, before either part is solved and timed
+fn play_if_asked(input: &str) {
+    if !std::env::args().any(|x| x == "--play") {
+        return;
+    }
+    if !io::stdin().is_terminal() {
+        eprintln!("--play needs an interactive terminal, skipping.");
+        return;
+    }
+    for part_2 in [false, true] {
+        // a bad input is reported by the parts themselves
+        let Ok(warehouse) = Warehouse::parse_input(input, part_2) else {
+            return;
+        };
+        if let Err(e) = play(&warehouse, part_2) {
+            eprintln!("--play stopped: {e}");
+            return;
+        }
+    }
+}
+
+pub fn part_one(input: &str) -> Result<usize, SolutionError> {
+    let mut warehouse = Warehouse::parse_input(input, false)?;
+
+    run(&mut warehouse, false);
 
     // warehouse.print(true);
 
-    Some(warehouse.coordinate_summation())
+    Ok(warehouse.coordinate_summation())
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let mut warehouse = Warehouse::parse_input(input, true);
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
+    let mut warehouse = Warehouse::parse_input(input, true)?;
 
-    warehouse.follow_robot_directions_part2();
+    run(&mut warehouse, true);
 
     // warehouse.print(true);
 
-    Some(warehouse.coordinate_summation())
+    Ok(warehouse.coordinate_summation())
 }
 
 #[cfg(test)]
@@ -307,6 +639,7 @@ mod tests {
             height: 2,
             robot_position: (0, 0).into(),
             directions: vec![],
+            journal: None,
         };
 
         assert_eq!(warehouse.coordinate_summation(), 104);
@@ -315,32 +648,79 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(10092));
+        assert_eq!(result, Ok(10092));
     }
     #[test]
     fn test_part_one_2() {
         let mut warehouse = Warehouse::parse_input(
             &advent_of_code::template::read_file_part("examples", DAY, 2),
             false,
-        );
+        )
+        .unwrap();
         warehouse.follow_robot_directions();
         warehouse.print(false);
         let result = part_one(&advent_of_code::template::read_file_part(
             "examples", DAY, 2,
         ));
-        assert_eq!(result, Some(2028));
+        assert_eq!(result, Ok(2028));
+    }
+
+    fn grid_text(warehouse: &Warehouse) -> String {
+        render_ansi(warehouse, &palette(true))
+    }
+
+    #[test]
+    fn test_step_and_undo() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let warehouse = Warehouse::parse_input(&input, true).unwrap();
+        let mut player = Player::new(warehouse.clone(), true);
+
+        player.apply(Command::ToEnd);
+        assert_eq!(player.warehouse.coordinate_summation(), 9021);
+        assert_eq!(player.next, warehouse.directions.len());
+
+        // an off-script move is undone first, without touching the listed moves
+        player.apply(Command::Move(Direction::Left));
+        player.apply(Command::Undo(1));
+        assert_eq!(player.next, warehouse.directions.len());
+
+        player.apply(Command::Undo(usize::MAX));
+        assert_eq!(player.next, 0);
+        assert_eq!(player.warehouse.robot_position, warehouse.robot_position);
+        assert_eq!(grid_text(&player.warehouse), grid_text(&warehouse));
+    }
+
+    #[test]
+    fn test_wide_push_highlight() {
+        let input = "#######\n#.....#\n#.OO..#\n#..O..#\n#..@..#\n#######\n\n^";
+        let mut warehouse = Warehouse::parse_input(input, true).unwrap();
+        let before = grid_text(&warehouse);
+        let step = warehouse.step(Direction::Up, true);
+
+        // the box in front of the robot shoves the one above it, but not its neighbour
+        let mut pushed = step.pushed_boxes();
+        pushed.sort_by_key(|p| (p.y(), p.x()));
+        let expected: Vec<Coord<usize>> = [(6, 1), (7, 1), (6, 2), (7, 2)]
+            .into_iter()
+            .map(Coord::from)
+            .collect();
+        assert_eq!(pushed, expected);
+        assert_eq!(warehouse.robot_position, Coord::new(6, 3));
+
+        warehouse.undo(&step);
+        assert_eq!(grid_text(&warehouse), before);
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(9021));
+        assert_eq!(result, Ok(9021));
     }
     #[test]
     fn test_part_two_2() {
         let result = part_two(&advent_of_code::template::read_file_part(
             "examples", DAY, 2,
         ));
-        assert_eq!(result, Some(1751));
+        assert_eq!(result, Ok(1751));
     }
 }
@@ -1,12 +1,10 @@
-#![allow(unused_assignments)]
-use aoc_mine::Coord;
-use num::Zero;
-
-use hashbrown::{HashMap, HashSet};
-
-use std::hash::Hash;
 use std::{cmp::Reverse, collections::BinaryHeap};
 
+use advent_of_code::CardinalDirection::{self, *};
+use advent_of_code::bitset::BitSet;
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::{Palette, Visualize, Visualizer};
+
 advent_of_code::solution!(16);
 
 #[cfg(not(target_env = "msvc"))]
@@ -16,246 +14,304 @@ use jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
-pub fn dijkstra<N, C, FN, IN, FS>(
-    start: &N,
-    mut successors: FN,
-    mut success: FS,
-) -> Option<(Vec<Vec<N>>, C)>
-where
-    N: Eq + Hash + Clone + Ord,
-    C: Zero + Ord + Copy,
-    FN: FnMut(&N) -> IN,
-    IN: IntoIterator<Item = (N, C)>,
-    FS: FnMut(&N) -> bool,
-{
-    dijkstra_internal(start, &mut successors, &mut success)
-}
-
-pub(crate) fn dijkstra_internal<N, C, FN, IN, FS>(
-    start: &N,
-    successors: &mut FN,
-    success: &mut FS,
-) -> Option<(Vec<Vec<N>>, C)>
-where
-    N: Eq + Hash + Clone + Ord,
-    C: Zero + Ord + Copy,
-    FN: FnMut(&N) -> IN,
-    IN: IntoIterator<Item = (N, C)>,
-    FS: FnMut(&N) -> bool,
-{
-    let zero = C::zero();
-    let mut dist = HashMap::new();
-    let mut parents: HashMap<N, Vec<N>> = HashMap::new();
-    let mut heap = BinaryHeap::new();
-
-    dist.insert(start.clone(), zero);
-    heap.push(Reverse((zero, start.clone())));
-
-    while let Some(Reverse((current_cost, current_node))) = heap.pop() {
-        if current_cost > *dist.get(&current_node).unwrap_or(&C::zero()) {
-            continue;
-        }
-
-        // goal reached
-        if success(&current_node) {
-            let paths = reconstruct_all_paths(&current_node, &parents);
-            return Some((paths, current_cost));
-        }
+const DIRECTIONS: [CardinalDirection; 4] = [North, South, West, East];
 
-        // iter over successors
-        for (neighbor, cost) in successors(&current_node) {
-            let new_cost = current_cost + cost;
-
-            if dist.get(&neighbor).is_none_or(|&c| new_cost < c) {
-                // update if a shorter path is found
-                dist.insert(neighbor.clone(), new_cost);
-                parents.insert(neighbor.clone(), vec![current_node.clone()]);
-                heap.push(Reverse((new_cost, neighbor)));
-            } else if dist.get(&neighbor).is_some_and(|&c| new_cost == c) {
-                // add if path cost is the same
-                parents
-                    .entry(neighbor.clone())
-                    .or_default()
-                    .push(current_node.clone());
-            }
-        }
+fn left_of(facing: CardinalDirection) -> CardinalDirection {
+    match facing {
+        North => West,
+        West => South,
+        South => East,
+        East => North,
     }
-
-    None
 }
 
-fn reconstruct_all_paths<N>(goal: &N, parents: &HashMap<N, Vec<N>>) -> Vec<Vec<N>>
-where
-    N: Eq + Hash + Clone,
-{
-    fn backtrack<N>(
-        node: &N,
-        parents: &HashMap<N, Vec<N>>,
-        current_path: &mut Vec<N>,
-        all_paths: &mut Vec<Vec<N>>,
-    ) where
-        N: Eq + Hash + Clone,
-    {
-        current_path.push(node.clone());
-        if let Some(parents_list) = parents.get(node) {
-            // recurse on all parents of the current node
-            for parent in parents_list {
-                backtrack(parent, parents, current_path, all_paths);
-            }
-        } else {
-            // if there are no parents, we've reached the start node
-            all_paths.push(current_path.clone());
-        }
-        current_path.pop(); // Backtrack
-    }
-
-    let mut all_paths = Vec::new();
-    let mut current_path = Vec::new();
+fn right_of(facing: CardinalDirection) -> CardinalDirection {
+    left_of(left_of(left_of(facing)))
+}
 
-    backtrack(goal, parents, &mut current_path, &mut all_paths);
+fn behind(facing: CardinalDirection) -> CardinalDirection {
+    left_of(left_of(facing))
+}
 
-    for path in &mut all_paths {
-        path.reverse();
-    }
+/// What the reindeer's moves cost, and which turns it can make.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Moves {
+    /// A step forward onto the next tile.
+    pub step: u32,
+    /// A quarter turn.
+    pub turn: u32,
+    /// Whether quarter turns can be made on the spot. Otherwise the reindeer can only turn
+    /// while stepping onto the tile to its side, paying for the step and the turn.
+    pub turn_in_place: bool,
+    /// Whether the reindeer can turn right round on the spot, for two quarter turns.
+    pub u_turn: bool,
+}
 
-    all_paths
+impl Moves {
+    /// The puzzle's rules: steps cost 1, and quarter turns on the spot 1000.
+    pub const REINDEER: Self = Self {
+        step: 1,
+        turn: 1000,
+        turn_in_place: true,
+        u_turn: false,
+    };
 }
 
-use advent_of_code::CardinalDirection::{self, *};
+/// A tile and the way the reindeer faces on it.
+pub type State = (usize, CardinalDirection);
 
+/// The maze on a dense grid. Tiles are numbered row by row, and a [`State`] by its tile and
+/// facing, so searches can keep their costs in plain `Vec`s.
 pub struct Maze {
-    pub walls: HashSet<Coord<usize>>,
+    walls: Vec<bool>,
     pub width: usize,
     pub height: usize,
-    pub goal: Coord<usize>,
-    pub position: Coord<usize>,
+    pub start: usize,
+    pub goal: usize,
     pub facing: CardinalDirection,
 }
 
 impl Maze {
-    pub fn parse_input(input: &str) -> Self {
-        let input = &input[0..input.len() - 1];
-        let width = input
-            .lines()
-            .next()
-            .unwrap()
-            .chars()
-            .collect::<Vec<_>>()
-            .len();
-        let height = input.lines().collect::<Vec<_>>().len();
-        let mut start: Option<Coord<usize>> = None;
-        let mut goal: Option<Coord<usize>> = None;
-        let walls: HashSet<Coord<usize>> = input
-            .lines()
-            .enumerate()
-            .flat_map(|(y, line)| {
-                line.chars()
-                    .enumerate()
-                    .filter_map(|(x, ch)| match ch {
-                        'S' => {
-                            start = Some((x, y).into());
-                            None
-                        }
-                        'E' => {
-                            goal = Some((x, y).into());
-                            None
-                        }
-                        '#' => Some((x, y).into()),
-                        _ => None,
-                    })
-                    .collect::<HashSet<Coord<usize>>>()
-            })
-            .collect();
+    pub fn parse_input(input: &str) -> Result<Self, SolutionError> {
+        let width = input.lines().next().map_or(0, str::len);
+        let mut walls = vec![];
+        let (mut start, mut goal) = (None, None);
+
+        for (y, line) in input.lines().enumerate() {
+            if line.len() != width {
+                return Err(SolutionError::at_line(
+                    y + 1,
+                    1,
+                    format!("expected a row {width} tiles wide"),
+                ));
+            }
+            for (x, ch) in line.chars().enumerate() {
+                match ch {
+                    'S' => start = Some(walls.len()),
+                    'E' => goal = Some(walls.len()),
+                    '#' | '.' => {}
+                    _ => {
+                        return Err(SolutionError::at_line(
+                            y + 1,
+                            x + 1,
+                            format!("unexpected tile {ch:?}"),
+                        ));
+                    }
+                }
+                walls.push(ch == '#');
+            }
+        }
 
-        Self {
+        Ok(Self {
+            height: walls.len() / width.max(1),
             walls,
             width,
-            height,
-            position: start.unwrap(),
-            goal: goal.unwrap(),
-            facing: CardinalDirection::East,
-        }
+            start: start.ok_or_else(|| SolutionError::new("the maze has no start tile"))?,
+            goal: goal.ok_or_else(|| SolutionError::new("the maze has no end tile"))?,
+            facing: East,
+        })
+    }
+
+    fn index(&self, (tile, facing): State) -> usize {
+        tile * 4 + facing as usize
     }
 
+    /// The open tile next to `tile` in direction `facing`, if there is one.
+    pub fn neighbor(&self, tile: usize, facing: CardinalDirection) -> Option<usize> {
+        let (x, y) = (tile % self.width, tile / self.width);
+        let next = match facing {
+            North => tile.checked_sub(self.width)?,
+            South => (y + 1 < self.height).then_some(tile + self.width)?,
+            West => (x > 0).then(|| tile - 1)?,
+            East => (x + 1 < self.width).then_some(tile + 1)?,
+        };
+        (!self.walls[next]).then_some(next)
+    }
+
+    /// The states one move on from `state`, with what each move costs.
     pub fn successors(
         &self,
-        position: &Coord<usize>,
-        facing: &CardinalDirection,
-    ) -> Vec<((Coord<usize>, CardinalDirection), u32)> {
-        let mut right_dir: Option<CardinalDirection> = None;
-        let mut left_dir: Option<CardinalDirection> = None;
-        let advancement_step: Option<Coord<usize>> = match facing {
-            North => {
-                right_dir = Some(East);
-                left_dir = Some(West);
-                position.up(Some(0))
+        (tile, facing): State,
+        moves: &Moves,
+    ) -> impl Iterator<Item = (State, u32)> {
+        let forward = self
+            .neighbor(tile, facing)
+            .map(|next| ((next, facing), moves.step));
+        let [left, right] = [left_of(facing), right_of(facing)].map(|side| {
+            if moves.turn_in_place {
+                Some(((tile, side), moves.turn))
+            } else {
+                self.neighbor(tile, side)
+                    .map(|next| ((next, side), moves.step + moves.turn))
             }
-            South => {
-                right_dir = Some(West);
-                left_dir = Some(East);
-                position.down(Some(self.height - 1))
+        });
+        let back = moves
+            .u_turn
+            .then_some(((tile, behind(facing)), 2 * moves.turn));
+
+        [forward, left, right, back].into_iter().flatten()
+    }
+
+    // Every move leaves the reindeer facing the way it went, so it came either from the same
+    // tile or from the one behind it.
+    fn predecessors(&self, state: State, moves: &Moves) -> impl Iterator<Item = (State, u32)> {
+        let (tile, facing) = state;
+        let tiles = [Some(tile), self.neighbor(tile, behind(facing))];
+        tiles
+            .into_iter()
+            .flatten()
+            .flat_map(|from| DIRECTIONS.map(|d| (from, d)))
+            .filter_map(move |from| {
+                self.successors(from, moves)
+                    .find(|&(to, _)| to == state)
+                    .map(|(_, cost)| (from, cost))
+            })
+    }
+
+    /// The lowest cost of reaching every state from the start, or `u32::MAX` where the
+    /// reindeer can't get to.
+    pub fn lowest_costs(&self, moves: &Moves) -> Vec<u32> {
+        let mut costs = vec![u32::MAX; self.walls.len() * 4];
+        let mut heap = BinaryHeap::new();
+        let start = (self.start, self.facing);
+        costs[self.index(start)] = 0;
+        heap.push(Reverse((0, self.index(start))));
+
+        while let Some(Reverse((cost, index))) = heap.pop() {
+            if cost > costs[index] {
+                continue;
             }
-            East => {
-                right_dir = Some(South);
-                left_dir = Some(North);
-                position.right(Some(self.width - 1))
+            let state = (index / 4, DIRECTIONS[index % 4]);
+            for (next, step) in self.successors(state, moves) {
+                let next = self.index(next);
+                let next_cost = cost + step;
+                if next_cost < costs[next] {
+                    costs[next] = next_cost;
+                    heap.push(Reverse((next_cost, next)));
+                }
             }
-            West => {
-                right_dir = Some(North);
-                left_dir = Some(South);
-                position.left(Some(0))
+        }
+
+        costs
+    }
+
+    fn best_score_from(&self, costs: &[u32]) -> Option<u32> {
+        DIRECTIONS
+            .iter()
+            .map(|&d| costs[self.index((self.goal, d))])
+            .min()
+            .filter(|&cost| cost != u32::MAX)
+    }
+
+    /// The lowest score a reindeer moving by `moves` can get.
+    pub fn best_score(&self, moves: &Moves) -> Option<u32> {
+        self.best_score_from(&self.lowest_costs(moves))
+    }
+
+    /// The best score, and every tile on at least one path that gets it.
+    ///
+    /// Works back from the end over the moves that kept to the lowest cost, so it finds every
+    /// best path without listing them one by one.
+    pub fn best_path_tiles(&self, moves: &Moves) -> Option<(u32, BitSet)> {
+        let costs = self.lowest_costs(moves);
+        let best = self.best_score_from(&costs)?;
+
+        let mut on_path = BitSet::new(costs.len());
+        let mut stack: Vec<State> = DIRECTIONS
+            .iter()
+            .map(|&d| (self.goal, d))
+            .filter(|&state| costs[self.index(state)] == best)
+            .collect();
+        stack
+            .iter()
+            .for_each(|&state| on_path.insert(self.index(state)));
+
+        while let Some(state) = stack.pop() {
+            let cost = costs[self.index(state)];
+            for (from, step) in self.predecessors(state, moves) {
+                let index = self.index(from);
+                if costs[index].checked_add(step) == Some(cost) && !on_path.contains(index) {
+                    on_path.insert(index);
+                    stack.push(from);
+                }
             }
-        };
-        let mut options: Vec<((Coord<usize>, CardinalDirection), u32)> = vec![
-            ((*position, left_dir.unwrap()), 1000),
-            ((*position, right_dir.unwrap()), 1000),
-        ];
-        if let Some(advancement_step) = advancement_step
-            && !self.walls.contains(&advancement_step)
-        {
-            options.push(((advancement_step, *facing), 1));
         }
 
-        options
+        let mut tiles = BitSet::new(self.walls.len());
+        on_path.iter().for_each(|index| tiles.insert(index / 4));
+        Some((best, tiles))
     }
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let maze = Maze::parse_input(input);
-    let start: (Coord<usize>, CardinalDirection) = (maze.position, maze.facing);
-
-    Some(
-        pathfinding::directed::dijkstra::dijkstra(
-            &start,
-            |(position, facing)| maze.successors(position, facing),
-            |&(position, _)| position == maze.goal,
-        )
-        .unwrap()
-        .1,
-    )
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Wall,
+    Open,
+    BestPath,
+    Start,
+    End,
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let maze = Maze::parse_input(input);
-    let start: (Coord<usize>, CardinalDirection) = (maze.position, maze.facing);
+/// The maze with every tile on some best path marked.
+pub struct BestPaths<'a> {
+    pub maze: &'a Maze,
+    pub tiles: &'a BitSet,
+}
 
-    let results = dijkstra(
-        &start,
-        |(position, facing)| maze.successors(position, facing),
-        |&(position, _)| position == maze.goal,
-    )
-    .unwrap();
+impl Visualize for BestPaths<'_> {
+    type Cell = Tile;
 
-    let mut visited: HashSet<Coord<usize>> = HashSet::new();
+    fn size(&self) -> (usize, usize) {
+        (self.maze.width, self.maze.height)
+    }
 
-    results.0.iter().for_each(|h| {
-        h.iter().for_each(|(p, _)| {
-            visited.insert(*p);
+    fn cell(&self, x: usize, y: usize) -> Tile {
+        let tile = y * self.maze.width + x;
+        if tile == self.maze.start {
+            Tile::Start
+        } else if tile == self.maze.goal {
+            Tile::End
+        } else if self.maze.walls[tile] {
+            Tile::Wall
+        } else if self.tiles.contains(tile) {
+            Tile::BestPath
+        } else {
+            Tile::Open
+        }
+    }
+}
+
+fn unreachable() -> SolutionError {
+    SolutionError::new("the reindeer can't reach the end tile")
+}
+
+pub fn part_one(input: &str) -> Result<u32, SolutionError> {
+    let maze = Maze::parse_input(input)?;
+    maze.best_score(&Moves::REINDEER).ok_or_else(unreachable)
+}
+
+// `--visualize` draws the maze with every best path on it
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
+    let maze = Maze::parse_input(input)?;
+    let (_, tiles) = maze
+        .best_path_tiles(&Moves::REINDEER)
+        .ok_or_else(unreachable)?;
+
+    if let Some(mut visualizer) = Visualizer::from_args(
+        DAY,
+        Palette::new('.', [25, 25, 35])
+            .with(Tile::Wall, '#', [110, 110, 120])
+            .with(Tile::BestPath, 'O', [240, 190, 60])
+            .with(Tile::Start, 'S', [80, 220, 120])
+            .with(Tile::End, 'E', [230, 70, 70]),
+    ) {
+        visualizer.finish(&BestPaths {
+            maze: &maze,
+            tiles: &tiles,
         });
-    });
+    }
 
-    let path_count = visited.len();
-    Some(path_count as u32)
+    Ok(tiles.len())
 }
 
 #[cfg(test)]
@@ -265,12 +321,56 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(7036));
+        assert_eq!(result, Ok(7036));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(45));
+        assert_eq!(result, Ok(45));
+    }
+
+    #[test]
+    fn test_custom_moves() {
+        // the reindeer starts facing the wall at the end of a corridor
+        let corridor = Maze::parse_input("#####\n#E.S#\n#####\n").unwrap();
+        let stepping = Moves {
+            turn_in_place: false,
+            ..Moves::REINDEER
+        };
+        assert_eq!(corridor.best_score(&Moves::REINDEER), Some(2002));
+        assert_eq!(corridor.best_score(&stepping), None);
+        let u_turn = Moves {
+            u_turn: true,
+            turn: 7,
+            ..stepping
+        };
+        assert_eq!(corridor.best_score(&u_turn), Some(16));
+
+        // with free turns the best score is the shortest walk
+        let maze =
+            Maze::parse_input(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+        let mut steps = vec![usize::MAX; maze.width * maze.height];
+        let mut queue = std::collections::VecDeque::from([maze.start]);
+        steps[maze.start] = 0;
+        while let Some(tile) = queue.pop_front() {
+            for next in DIRECTIONS.iter().filter_map(|&d| maze.neighbor(tile, d)) {
+                if steps[next] == usize::MAX {
+                    steps[next] = steps[tile] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        let free_turns = Moves {
+            turn: 0,
+            ..Moves::REINDEER
+        };
+        let (score, tiles) = maze.best_path_tiles(&free_turns).unwrap();
+        assert_eq!(score as usize, steps[maze.goal]);
+        assert!(tiles.contains(maze.start) && tiles.contains(maze.goal));
+
+        // turning while stepping can't cost less than turning first
+        let stepping_score = maze.best_score(&stepping).unwrap();
+        assert!(stepping_score >= 7036);
     }
 }
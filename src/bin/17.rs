@@ -1,206 +1,323 @@
 advent_of_code::solution!(17);
 
+use advent_of_code::parse::{finish, unsigned, ParseError};
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::flag_once;
 use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
-    character::complete::{digit1, multispace0},
-    combinator::map_res,
+    character::complete::{char, multispace0},
     multi::separated_list1,
     sequence::{preceded, separated_pair, terminated},
     IResult,
 };
-use std::str::FromStr;
+use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq)]
-struct Registers {
+// plenty for the puzzle programs, which loop once per output digit
+const CYCLE_LIMIT: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
     pub a: u64,
     pub b: u64,
     pub c: u64,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-struct OpCodeProgram {
-    registers: Registers,
-    program: Vec<u8>,
-    output: Vec<u64>,
-    position: usize,
-    halt: bool,
+// octal, since the programs work on three bits at a time
+impl Display for Registers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A={:o} B={:o} C={:o}", self.a, self.b, self.c)
+    }
 }
 
-// hacked up my copy of the compute function to return true/false as candidate
-// options for the outputs
-fn compute(program: &[u8], inst_position: usize, reg_a: u64) -> bool {
-    let mut registers: [u64; 3] = [reg_a, 0, 0];
-
-    for position in 0..(program.len() / 2) {
-        let left = program[position * 2];
-        let right = program[position * 2 + 1] as u64;
-        let operand = get_operand(&right, &registers);
-        match left {
-            0 => {
-                // adv - division
-                registers[0] >>= operand;
-            }
-            1 => {
-                // bxl - bitwise XOR B and operand
-                registers[1] ^= right & 0b111;
-            }
-            2 => {
-                // bst - combo modulo 8
-                registers[1] = operand % 8;
-            }
-            3 => {
-                // jnz - jump
-                if registers[0] != 0 {
-                    return compute(program, inst_position + 1, registers[0]);
-                }
-            }
-            4 => {
-                // bxc - bitwise XOR B C
-                registers[1] ^= registers[2];
-            }
-            5 => {
-                // out - combo modulo 8 output
-                let check: u8 = (operand % 8) as u8;
-                if check != program[inst_position] {
-                    return false;
-                }
-                if inst_position == (program.len() - 1) {
-                    return true;
-                }
-            }
-            6 => {
-                // bdv - adv stored B
-                registers[1] = registers[0] >> operand;
-            }
-            7 => {
-                // cdv - adv stored C
-                registers[2] = registers[0] >> operand;
-            }
-            _ => unreachable!("{left} is invalid opcode"),
-        };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Adv,
+    Bxl,
+    Bst,
+    Jnz,
+    Bxc,
+    Out,
+    Bdv,
+    Cdv,
+}
+
+impl Instruction {
+    pub fn from_opcode(opcode: u8) -> Option<Self> {
+        Some(match opcode {
+            0 => Self::Adv,
+            1 => Self::Bxl,
+            2 => Self::Bst,
+            3 => Self::Jnz,
+            4 => Self::Bxc,
+            5 => Self::Out,
+            6 => Self::Bdv,
+            7 => Self::Cdv,
+            _ => return None,
+        })
     }
 
-    false
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Adv => "adv",
+            Self::Bxl => "bxl",
+            Self::Bst => "bst",
+            Self::Jnz => "jnz",
+            Self::Bxc => "bxc",
+            Self::Out => "out",
+            Self::Bdv => "bdv",
+            Self::Cdv => "cdv",
+        }
+    }
+
+    /// Whether the operand is a combo operand rather than a literal.
+    pub fn takes_combo(&self) -> bool {
+        matches!(
+            self,
+            Self::Adv | Self::Bst | Self::Out | Self::Bdv | Self::Cdv
+        )
+    }
 }
-fn get_operand(right: &u64, registers: &[u64; 3]) -> u64 {
-    match right {
-        0..=3 => *right,
-        4 => registers[0],
-        5 => registers[1],
-        6 => registers[2],
-        _ => unreachable!("Invalid operand {right}"),
+
+/// A decoded combo operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combo {
+    Literal(u8),
+    A,
+    B,
+    C,
+    Reserved,
+}
+
+impl Combo {
+    pub fn decode(operand: u8) -> Self {
+        match operand {
+            0..=3 => Self::Literal(operand),
+            4 => Self::A,
+            5 => Self::B,
+            6 => Self::C,
+            _ => Self::Reserved,
+        }
     }
 }
 
-impl OpCodeProgram {
-    fn run(&mut self, stop_length: Option<usize>) {
-        let stop_length = stop_length.unwrap_or(9);
-        let mut steps = 0;
-        while !self.halt && self.output.len() <= stop_length && steps < 100 {
-            self.compute();
-            steps += 1;
+impl Display for Combo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Literal(v) => write!(f, "{v}"),
+            Self::A => write!(f, "A"),
+            Self::B => write!(f, "B"),
+            Self::C => write!(f, "C"),
+            Self::Reserved => write!(f, "<reserved>"),
         }
     }
+}
 
-    fn print_output(&self) -> String {
-        self.output.iter().join(",")
+/// Formats one instruction, e.g. `adv 3`, `out B` or `bxc`.
+pub fn format_instruction(instruction: Instruction, operand: u8) -> String {
+    match instruction {
+        Instruction::Bxc => instruction.mnemonic().to_string(),
+        i if i.takes_combo() => format!("{} {}", i.mnemonic(), Combo::decode(operand)),
+        i => format!("{} {operand}", i.mnemonic()),
+    }
+}
+
+/// One line per instruction, prefixed with its address.
+pub fn disassemble(program: &[u8]) -> Vec<String> {
+    program
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            let line = match (Instruction::from_opcode(pair[0]), pair.get(1)) {
+                (Some(instruction), Some(&operand)) => format_instruction(instruction, operand),
+                _ => format!("??? {}", pair.iter().join(",")),
+            };
+            format!("{:02}: {line}", i * 2)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    InvalidOpcode { ip: usize, opcode: u8 },
+    ReservedOperand { ip: usize },
+    CycleLimit(usize),
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidOpcode { ip, opcode } => write!(f, "invalid opcode {opcode} at {ip}"),
+            Self::ReservedOperand { ip } => write!(f, "reserved combo operand 7 at {ip}"),
+            Self::CycleLimit(limit) => write!(f, "program did not halt within {limit} cycles"),
+        }
+    }
+}
+
+impl From<VmError> for SolutionError {
+    fn from(e: VmError) -> Self {
+        SolutionError::new(e.to_string())
     }
+}
+
+/// What a single executed instruction did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub ip: usize,
+    pub instruction: Instruction,
+    pub operand: u8,
+    pub before: Registers,
+    pub after: Registers,
+    pub output: Option<u8>,
+}
 
-    fn get_operand(&self, right: &u8) -> u64 {
-        match right {
-            0..=3 => *right as u64,
-            4 => self.registers.a,
-            5 => self.registers.b,
-            6 => self.registers.c,
-            _ => unreachable!("Invalid operand {right}"),
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}: {:<7} {} -> {}",
+            self.ip,
+            format_instruction(self.instruction, self.operand),
+            self.before,
+            self.after
+        )?;
+        if let Some(out) = self.output {
+            write!(f, " out {out}")?;
         }
+        Ok(())
     }
+}
+
+/// The three-bit computer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vm {
+    pub registers: Registers,
+    pub program: Vec<u8>,
+    pub ip: usize,
+    pub output: Vec<u8>,
+}
 
-    fn compute(&mut self) {
-        let left = self.program.get(self.position);
-        let right = self.program.get(self.position + 1);
-        if left.is_none() || right.is_none() {
-            self.halt = true;
-            return;
+impl Vm {
+    pub fn new(registers: Registers, program: Vec<u8>) -> Self {
+        Self {
+            registers,
+            program,
+            ip: 0,
+            output: Vec::new(),
         }
+    }
 
-        let (left, right) = (left.unwrap(), right.unwrap());
-        let operand = self.get_operand(right);
+    pub fn is_halted(&self) -> bool {
+        self.ip + 1 >= self.program.len()
+    }
 
-        let mut advance = true;
+    fn combo(&self, operand: u8) -> Result<u64, VmError> {
+        match Combo::decode(operand) {
+            Combo::Literal(v) => Ok(v as u64),
+            Combo::A => Ok(self.registers.a),
+            Combo::B => Ok(self.registers.b),
+            Combo::C => Ok(self.registers.c),
+            Combo::Reserved => Err(VmError::ReservedOperand { ip: self.ip }),
+        }
+    }
 
-        match left {
-            0 => {
-                // adv - division
-                self.registers.a >>= operand;
-            }
-            1 => {
-                // bxl - bitwise XOR B and operand
-                self.registers.b ^= *right as u64 & 0b111;
-            }
-            2 => {
-                // bst - combo modulo 8
-                self.registers.b = operand % 8;
-            }
-            3 => {
-                // jnz - jump
+    /// Executes one instruction. Returns `None` once the program has halted.
+    pub fn step(&mut self) -> Result<Option<TraceEntry>, VmError> {
+        if self.is_halted() {
+            return Ok(None);
+        }
+
+        let ip = self.ip;
+        let opcode = self.program[ip];
+        let operand = self.program[ip + 1];
+        let instruction =
+            Instruction::from_opcode(opcode).ok_or(VmError::InvalidOpcode { ip, opcode })?;
+        let before = self.registers;
+        let mut output = None;
+        let mut next_ip = ip + 2;
+
+        // shifts of 64 or more clear the register rather than overflowing
+        let shift = |value: u64, by: u64| if by >= 64 { 0 } else { value >> by };
+
+        match instruction {
+            Instruction::Adv => self.registers.a = shift(self.registers.a, self.combo(operand)?),
+            Instruction::Bxl => self.registers.b ^= operand as u64,
+            Instruction::Bst => self.registers.b = self.combo(operand)? % 8,
+            Instruction::Jnz => {
                 if self.registers.a != 0 {
-                    self.position = *right as usize;
-                    advance = false;
+                    next_ip = operand as usize;
                 }
             }
-            4 => {
-                // bxc - bitwise XOR B C
-                self.registers.b ^= self.registers.c;
-            }
-            5 => {
-                // out - combo modulo 8 output
-                self.output.push(operand % 8);
-            }
-            6 => {
-                // bdv - adv stored B
-                self.registers.b = self.registers.a >> operand;
-            }
-            7 => {
-                // cdv - adv stored C
-                self.registers.c = self.registers.a >> operand;
+            Instruction::Bxc => self.registers.b ^= self.registers.c,
+            Instruction::Out => {
+                let value = (self.combo(operand)? % 8) as u8;
+                self.output.push(value);
+                output = Some(value);
             }
-            _ => unreachable!("{left} is invalid opcode"),
-        };
+            Instruction::Bdv => self.registers.b = shift(self.registers.a, self.combo(operand)?),
+            Instruction::Cdv => self.registers.c = shift(self.registers.a, self.combo(operand)?),
+        }
 
-        if advance {
-            self.position += 2;
+        self.ip = next_ip;
+
+        Ok(Some(TraceEntry {
+            ip,
+            instruction,
+            operand,
+            before,
+            after: self.registers,
+            output,
+        }))
+    }
+
+    /// Runs until the program halts, failing if it takes more than `cycle_limit` instructions.
+    pub fn run(&mut self, cycle_limit: usize) -> Result<&[u8], VmError> {
+        for _ in 0..cycle_limit {
+            if self.step()?.is_none() {
+                return Ok(&self.output);
+            }
+        }
+        if self.is_halted() {
+            Ok(&self.output)
+        } else {
+            Err(VmError::CycleLimit(cycle_limit))
         }
     }
-}
 
-fn parse_u64(input: &str) -> IResult<&str, u64> {
-    map_res(digit1, |s: &str| u64::from_str(s))(input)
-}
+    /// Like [`Vm::run`], but records every executed instruction.
+    pub fn run_traced(&mut self, cycle_limit: usize) -> Result<Vec<TraceEntry>, VmError> {
+        let mut trace = Vec::new();
+        for _ in 0..cycle_limit {
+            match self.step()? {
+                Some(entry) => trace.push(entry),
+                None => return Ok(trace),
+            }
+        }
+        if self.is_halted() {
+            Ok(trace)
+        } else {
+            Err(VmError::CycleLimit(cycle_limit))
+        }
+    }
 
-fn parse_u8(input: &str) -> IResult<&str, u8> {
-    map_res(digit1, |s: &str| u8::from_str(s))(input)
+    pub fn print_output(&self) -> String {
+        self.output.iter().join(",")
+    }
 }
 
-fn parse_register(input: &str) -> IResult<&str, (char, u64)> {
-    let (input, _) = tag("Register ")(input)?;
-    separated_pair(
-        nom::character::complete::one_of("ABC"),
-        tag(": "),
-        parse_u64,
-    )(input)
+fn parse_register<'a>(label: char) -> impl FnMut(&'a str) -> IResult<&'a str, u64> {
+    move |input| {
+        let (input, _) = tag("Register ")(input)?;
+        let (input, _) = char(label)(input)?;
+        preceded(tag(": "), unsigned)(input)
+    }
 }
 
 fn parse_registers(input: &str) -> IResult<&str, Registers> {
     let (input, _) = multispace0(input)?;
-    let (input, (a_label, a_value)) = terminated(parse_register, tag("\n"))(input)?;
-    let (input, (b_label, b_value)) = terminated(parse_register, tag("\n"))(input)?;
-    let (input, (c_label, c_value)) = terminated(parse_register, tag("\n"))(input)?;
-
-    assert_eq!(a_label, 'A');
-    assert_eq!(b_label, 'B');
-    assert_eq!(c_label, 'C');
+    let (input, a_value) = terminated(parse_register('A'), tag("\n"))(input)?;
+    let (input, b_value) = terminated(parse_register('B'), tag("\n"))(input)?;
+    let (input, c_value) = terminated(parse_register('C'), tag("\n"))(input)?;
 
     Ok((
         input,
@@ -215,70 +332,212 @@ fn parse_registers(input: &str) -> IResult<&str, Registers> {
 fn parse_program(input: &str) -> IResult<&str, Vec<u8>> {
     preceded(
         tag("Program: "),
-        separated_list1(tag(","), preceded(multispace0, parse_u8)),
+        separated_list1(tag(","), preceded(multispace0, unsigned)),
     )(input)
 }
 
-fn parse_input(input: &str) -> IResult<&str, OpCodeProgram> {
-    let (input, registers) = parse_registers(input)?;
-    let (input, _) = tag("\n")(input)?;
-    let (input, program) = parse_program(input)?;
-
-    Ok((
+fn parse_input(input: &str) -> Result<Vm, ParseError> {
+    let (registers, program) = finish(
         input,
-        OpCodeProgram {
-            registers,
-            program,
-            output: Vec::new(),
-            position: 0,
-            halt: false,
-        },
-    ))
+        separated_pair(parse_registers, tag("\n"), parse_program),
+    )?;
+
+    Ok(Vm::new(registers, program))
 }
 
-pub fn part_one(input: &str) -> Option<String> {
-    let mut program = parse_input(input).unwrap().1;
+pub fn part_one(input: &str) -> Result<String, SolutionError> {
+    let mut vm = parse_input(input)?;
+
+    if flag_once("--trace") {
+        println!();
+        disassemble(&vm.program)
+            .iter()
+            .for_each(|line| println!("{line}"));
+        println!();
+        vm.run_traced(CYCLE_LIMIT)?
+            .iter()
+            .for_each(|entry| println!("{entry}"));
+    } else {
+        vm.run(CYCLE_LIMIT)?;
+    }
 
-    program.run(None);
-    Some(program.print_output())
+    Ok(vm.print_output())
 }
 
-pub fn part_two(input: &str) -> Option<u64> {
-    let program = parse_input(input).unwrap().1;
-    let mut search: Vec<u64> = vec![0];
-
-    // for each output, only the last three bits of "a" matter
-    // find each combination that works as each step, then shift
-    // them over by three bits to make room for the next test
-    for target in (0..program.program.len()).rev() {
-        // println!("\n");
-        // search.iter().for_each(|s| println!("{:b}", s));
-        let mut next_search_space: Vec<u64> = Vec::new();
-
-        // 111 is 7 in binary, so to test each possible bitmask, we only need
-        // to test 0->7
-        // 0 -> 000
-        // 1 -> 001
-        // 2 -> 010
-        // 3 -> 011
-        // 4 -> 100
-        // 5 -> 101
-        // 6 -> 110
-        // 7 -> 111
-        let search_candidates = search.iter().flat_map(|a| (0..8).map(move |i| a + i));
-        for a in search_candidates {
-            if compute(&program.program, target, a) {
-                if target == 0 {
-                    return Some(a);
-                }
-                // only the lowest three bits matter for each output, so shift over
-                next_search_space.push(a << 3);
+/// Why a program can't be handled by [`find_register_a`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unsupported {
+    Empty,
+    InvalidInstruction { ip: usize },
+    NoFinalLoop,
+    ExtraJump { ip: usize },
+    ShiftCount(usize),
+    ShiftOperand { ip: usize },
+    NoOutput,
+    CarriedRegister { ip: usize, register: char },
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unsupported program: ")?;
+        match self {
+            Self::Empty => write!(f, "it is empty"),
+            Self::InvalidInstruction { ip } => write!(f, "invalid instruction at {ip}"),
+            Self::NoFinalLoop => write!(f, "it must end with `jnz 0`"),
+            Self::ExtraJump { ip } => write!(
+                f,
+                "the only jump must be the final `jnz 0`, found one at {ip}"
+            ),
+            Self::ShiftCount(n) => write!(f, "A must be shifted by exactly one `adv`, found {n}"),
+            Self::ShiftOperand { ip } => {
+                write!(f, "the `adv` at {ip} must shift by a literal 1 to 3")
             }
+            Self::NoOutput => write!(f, "the loop never outputs anything"),
+            Self::CarriedRegister { ip, register } => write!(
+                f,
+                "{register} is read at {ip} before being set, so it carries over between iterations"
+            ),
+        }
+    }
+}
+
+impl From<Unsupported> for SolutionError {
+    fn from(e: Unsupported) -> Self {
+        SolutionError::new(e.to_string())
+    }
+}
+
+/// The loop structure the quine search relies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopShape {
+    /// Bits dropped from A by the single `adv` each iteration.
+    pub shift: u8,
+    /// `out` instructions executed per iteration.
+    pub outputs_per_iteration: usize,
+}
+
+/// Checks that `program` is a single loop which ends in `jnz 0`, drops a fixed number of bits
+/// from A each time round, and derives B and C from A afresh in every iteration.
+///
+/// Those are the conditions under which each iteration's output depends only on the bits of A
+/// that are still left, so A can be built up a digit at a time from the last output backwards.
+pub fn analyse(program: &[u8]) -> Result<LoopShape, Unsupported> {
+    if program.is_empty() {
+        return Err(Unsupported::Empty);
+    }
+    let instructions = program
+        .chunks(2)
+        .enumerate()
+        .map(
+            |(i, pair)| match (Instruction::from_opcode(pair[0]), pair.get(1)) {
+                (Some(instruction), Some(&operand)) => Ok((i * 2, instruction, operand)),
+                _ => Err(Unsupported::InvalidInstruction { ip: i * 2 }),
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (body, last) = instructions.split_at(instructions.len() - 1);
+    if !matches!(last[0], (_, Instruction::Jnz, 0)) {
+        return Err(Unsupported::NoFinalLoop);
+    }
+
+    let mut shifts = Vec::new();
+    let mut outputs = 0;
+    let (mut b_set, mut c_set) = (false, false);
+
+    for &(ip, instruction, operand) in body {
+        let combo = Combo::decode(operand);
+        if instruction.takes_combo() && combo == Combo::Reserved {
+            return Err(Unsupported::InvalidInstruction { ip });
+        }
+        let reads_b = match instruction {
+            Instruction::Bxl | Instruction::Bxc => true,
+            i => i.takes_combo() && combo == Combo::B,
+        };
+        let reads_c = match instruction {
+            Instruction::Bxc => true,
+            i => i.takes_combo() && combo == Combo::C,
+        };
+        if reads_b && !b_set {
+            return Err(Unsupported::CarriedRegister { ip, register: 'B' });
+        }
+        if reads_c && !c_set {
+            return Err(Unsupported::CarriedRegister { ip, register: 'C' });
         }
-        search = next_search_space;
+
+        match instruction {
+            Instruction::Jnz => return Err(Unsupported::ExtraJump { ip }),
+            Instruction::Adv => shifts.push((ip, combo)),
+            Instruction::Out => outputs += 1,
+            Instruction::Bst | Instruction::Bdv => b_set = true,
+            Instruction::Cdv => c_set = true,
+            Instruction::Bxl | Instruction::Bxc => {}
+        }
+    }
+
+    let shift = match shifts[..] {
+        [(_, Combo::Literal(n @ 1..=3))] => n,
+        [(ip, _)] => return Err(Unsupported::ShiftOperand { ip }),
+        _ => return Err(Unsupported::ShiftCount(shifts.len())),
+    };
+    if outputs == 0 {
+        return Err(Unsupported::NoOutput);
+    }
+
+    Ok(LoopShape {
+        shift,
+        outputs_per_iteration: outputs,
+    })
+}
+
+/// The smallest value of register A for which `program` outputs exactly `target`.
+///
+/// Works from the last iteration backwards, trying each digit of `shift` bits below the digits
+/// already found and backtracking when a prefix leads nowhere.
+pub fn find_register_a(program: &[u8], target: &[u8]) -> Result<Option<u64>, SolutionError> {
+    let shape = analyse(program)?;
+    if target.is_empty() || !target.len().is_multiple_of(shape.outputs_per_iteration) {
+        return Ok(None);
+    }
+    let iterations = target.len() / shape.outputs_per_iteration;
+    if iterations * shape.shift as usize > 64 {
+        return Ok(None);
     }
 
-    None
+    search_digit(program, target, shape, iterations, 0)
+}
+
+fn search_digit(
+    program: &[u8],
+    target: &[u8],
+    shape: LoopShape,
+    iteration: usize,
+    prefix: u64,
+) -> Result<Option<u64>, SolutionError> {
+    if iteration == 0 {
+        return Ok(Some(prefix));
+    }
+    let expected = &target[(iteration - 1) * shape.outputs_per_iteration..];
+
+    for digit in 0..1 << shape.shift {
+        let a = prefix << shape.shift | digit;
+        let mut vm = Vm::new(Registers { a, b: 0, c: 0 }, program.to_vec());
+        if vm.run(CYCLE_LIMIT)? != expected {
+            continue;
+        }
+        if let Some(found) = search_digit(program, target, shape, iteration - 1, a)? {
+            return Ok(Some(found));
+        }
+    }
+
+    Ok(None)
+}
+
+pub fn part_two(input: &str) -> Result<u64, SolutionError> {
+    let vm = parse_input(input)?;
+
+    find_register_a(&vm.program, &vm.program)?
+        .ok_or_else(|| SolutionError::new("no value of register A makes the program output itself"))
 }
 
 #[cfg(test)]
@@ -290,7 +549,7 @@ mod tests {
         let result = part_one(&advent_of_code::template::read_file_part(
             "examples", DAY, 1,
         ));
-        assert_eq!(result, Some("4,6,3,5,6,3,5,2,1,0".to_string()));
+        assert_eq!(result, Ok("4,6,3,5,6,3,5,2,1,0".to_string()));
     }
 
     #[test]
@@ -298,6 +557,154 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file_part(
             "examples", DAY, 2,
         ));
-        assert_eq!(result, Some(117440));
+        assert_eq!(result, Ok(117440));
+    }
+
+    #[test]
+    fn test_small_programs() {
+        // examples from the puzzle text
+        let mut vm = Vm::new(Registers { a: 0, b: 0, c: 9 }, vec![2, 6]);
+        vm.run(CYCLE_LIMIT).unwrap();
+        assert_eq!(vm.registers.b, 1);
+
+        let mut vm = Vm::new(Registers { a: 10, b: 0, c: 0 }, vec![5, 0, 5, 1, 5, 4]);
+        assert_eq!(vm.run(CYCLE_LIMIT).unwrap(), &[0, 1, 2]);
+
+        let mut vm = Vm::new(
+            Registers {
+                a: 0,
+                b: 2024,
+                c: 43690,
+            },
+            vec![4, 0],
+        );
+        vm.run(CYCLE_LIMIT).unwrap();
+        assert_eq!(vm.registers.b, 44354);
+
+        // a jump to itself never halts
+        let mut vm = Vm::new(Registers { a: 1, b: 0, c: 0 }, vec![3, 0]);
+        assert_eq!(vm.run(10), Err(VmError::CycleLimit(10)));
+
+        let mut vm = Vm::new(Registers { a: 1, b: 0, c: 0 }, vec![5, 7]);
+        assert_eq!(vm.run(10), Err(VmError::ReservedOperand { ip: 0 }));
+    }
+
+    #[test]
+    fn test_disassemble_and_trace() {
+        let vm = parse_input(&advent_of_code::template::read_file_part(
+            "examples", DAY, 2,
+        ))
+        .unwrap();
+        assert_eq!(
+            disassemble(&vm.program),
+            vec!["00: adv 3", "02: out A", "04: jnz 0"]
+        );
+
+        let mut vm = Vm::new(Registers { a: 8, b: 0, c: 0 }, vm.program);
+        let trace = vm.run_traced(CYCLE_LIMIT).unwrap();
+        assert_eq!(trace.len(), 6);
+        assert_eq!(trace[1].output, Some(1));
+        assert_eq!(trace[5].after.a, 0);
+        assert_eq!(
+            trace[0].to_string(),
+            "00: adv 3   A=10 B=0 C=0 -> A=1 B=0 C=0"
+        );
+        assert_eq!(vm.print_output(), "1,0");
+    }
+
+    // runs `program` from `a`, then checks the search finds the smallest A giving the same output
+    fn check_round_trip(program: &[u8], a: u64) {
+        let mut vm = Vm::new(Registers { a, b: 0, c: 0 }, program.to_vec());
+        let output = vm.run(CYCLE_LIMIT).unwrap().to_vec();
+
+        let found = find_register_a(program, &output).unwrap().unwrap();
+        assert!(found <= a);
+        let mut vm = Vm::new(
+            Registers {
+                a: found,
+                b: 0,
+                c: 0,
+            },
+            program.to_vec(),
+        );
+        assert_eq!(vm.run(CYCLE_LIMIT).unwrap(), output);
+    }
+
+    #[test]
+    fn test_analyse() {
+        // the usual shape: B and C derived from A, one output, A shifted by 3
+        let typical = [2, 4, 1, 5, 7, 5, 1, 6, 0, 3, 4, 0, 5, 5, 3, 0];
+        assert_eq!(
+            analyse(&typical),
+            Ok(LoopShape {
+                shift: 3,
+                outputs_per_iteration: 1
+            })
+        );
+        // two bits at a time, two outputs per iteration
+        assert_eq!(
+            analyse(&[2, 4, 5, 5, 1, 3, 5, 5, 0, 2, 3, 0]),
+            Ok(LoopShape {
+                shift: 2,
+                outputs_per_iteration: 2
+            })
+        );
+
+        assert_eq!(analyse(&[0, 3, 5, 4]), Err(Unsupported::NoFinalLoop));
+        assert_eq!(analyse(&[0, 3, 5, 4, 3, 2]), Err(Unsupported::NoFinalLoop));
+        assert_eq!(
+            analyse(&[0, 3, 3, 0, 5, 4, 3, 0]),
+            Err(Unsupported::ExtraJump { ip: 2 })
+        );
+        assert_eq!(analyse(&[5, 4, 3, 0]), Err(Unsupported::ShiftCount(0)));
+        assert_eq!(
+            analyse(&[0, 4, 5, 4, 3, 0]),
+            Err(Unsupported::ShiftOperand { ip: 0 })
+        );
+        assert_eq!(
+            analyse(&[1, 1, 5, 5, 0, 3, 3, 0]),
+            Err(Unsupported::CarriedRegister {
+                ip: 0,
+                register: 'B'
+            })
+        );
+        assert_eq!(
+            analyse(&[2, 4, 4, 0, 5, 5, 0, 3, 3, 0]),
+            Err(Unsupported::CarriedRegister {
+                ip: 2,
+                register: 'C'
+            })
+        );
+        assert!(
+            part_two("Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: 5,4,3,0\n")
+                .unwrap_err()
+                .message
+                .starts_with("unsupported program")
+        );
+    }
+
+    #[test]
+    fn test_find_register_a() {
+        check_round_trip(
+            &[2, 4, 1, 5, 7, 5, 1, 6, 0, 3, 4, 0, 5, 5, 3, 0],
+            0o3_615_207_442,
+        );
+        check_round_trip(
+            &[2, 4, 1, 3, 7, 5, 0, 3, 1, 5, 4, 4, 5, 5, 3, 0],
+            0o7_000_000_123,
+        );
+        check_round_trip(&[2, 4, 5, 5, 1, 3, 5, 5, 0, 2, 3, 0], 0b10_11_01_00_11);
+        check_round_trip(&[0, 1, 5, 4, 3, 0], 0b1011_0110);
+
+        // program 0,1,5,4,3,0 can only ever output shrinking values, so it can't be a quine
+        assert_eq!(
+            find_register_a(&[0, 1, 5, 4, 3, 0], &[0, 1, 5, 4, 3, 0]),
+            Ok(None)
+        );
+        // odd output lengths can't come from two outputs per iteration
+        assert_eq!(
+            find_register_a(&[2, 4, 5, 5, 1, 3, 5, 5, 0, 2, 3, 0], &[1, 2, 3]),
+            Ok(None)
+        );
     }
 }
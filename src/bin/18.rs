@@ -1,55 +1,101 @@
 advent_of_code::solution!(18);
 
-use std::{
-    cmp::Ordering,
-    collections::{HashSet, VecDeque},
-};
-
-fn parse_input(input: &str) -> Vec<(usize, usize)> {
-    input
-        .lines()
-        .map(|line| {
-            let parts: Vec<_> = line.split(',').collect();
-            (
-                parts[0].parse::<usize>().unwrap(),
-                parts[1].parse::<usize>().unwrap(),
-            )
-        })
-        .collect()
-}
+use std::collections::VecDeque;
 
-fn find_shortest_path(byte_positions: &[(usize, usize)], blocks: usize) -> Option<usize> {
-    let mut corrupted = HashSet::new();
-    let grid_size = if byte_positions.len() > 100 { 71 } else { 7 };
+use advent_of_code::template::SolutionError;
 
-    for &(x, y) in byte_positions.iter().take(blocks) {
-        corrupted.insert((x, y));
-    }
+/// Width and height of the memory space, unless the input says otherwise.
+const GRID_SIZE: usize = 71;
+/// Bytes that fall before part one's walk, unless the input says otherwise.
+const FALLEN: usize = 1024;
 
-    let directions = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+/// The memory space and the bytes falling into it, in order.
+#[derive(Debug)]
+struct Memory {
+    grid_size: usize,
+    fallen: usize,
+    bytes: Vec<(usize, usize)>,
+}
 
-    let mut queue = VecDeque::new();
-    let mut visited = HashSet::new();
+// `size <n>, fallen <n>`
+fn parse_header(line: &str) -> Option<(usize, usize)> {
+    let (size, fallen) = line.strip_prefix("size ")?.split_once(", fallen ")?;
+    let size = size.parse().ok().filter(|&size| size > 0)?;
+    Some((size, fallen.parse().ok()?))
+}
 
-    queue.push_back((0, 0, 0)); // (x, y, steps)
-    visited.insert((0, 0));
+// An optional first line `size <n>, fallen <n>` replaces the puzzle's space and byte count,
+// which the smaller example needs.
+fn parse_input(input: &str) -> Result<Memory, SolutionError> {
+    let mut lines = input.lines().enumerate().peekable();
+    let (grid_size, fallen) = match lines.next_if(|(_, line)| line.starts_with("size")) {
+        Some((_, header)) => parse_header(header)
+            .ok_or_else(|| SolutionError::at_line(1, 1, "expected size <n>, fallen <n>"))?,
+        None => (GRID_SIZE, FALLEN),
+    };
+
+    let bytes = lines
+        .map(|(i, line)| {
+            let position = line
+                .split_once(',')
+                .and_then(|(x, y)| Some((x.parse::<usize>().ok()?, y.parse::<usize>().ok()?)));
+            match position {
+                Some((x, y)) if x < grid_size && y < grid_size => Ok((x, y)),
+                Some(_) => Err(SolutionError::at_line(
+                    i + 1,
+                    1,
+                    format!("{line} is outside the {grid_size}x{grid_size} memory space"),
+                )),
+                None => Err(SolutionError::at_line(i + 1, 1, "expected x,y")),
+            }
+        })
+        .collect::<Result<_, _>>()?;
 
-    while let Some((x, y, steps)) = queue.pop_front() {
-        if (x, y) == (grid_size - 1, grid_size - 1) {
-            return Some(steps);
-        }
+    Ok(Memory {
+        grid_size,
+        fallen,
+        bytes,
+    })
+}
+
+/// Steps from the top left to the bottom right corner of a `grid_size` square after the
+/// first `fallen` bytes have landed, or `None` if they cut the way off.
+pub fn shortest_path(
+    byte_positions: &[(usize, usize)],
+    grid_size: usize,
+    fallen: usize,
+) -> Option<usize> {
+    let mut corrupted = vec![false; grid_size * grid_size];
+    for &(x, y) in byte_positions.iter().take(fallen) {
+        corrupted[y * grid_size + x] = true;
+    }
 
-        for &(dx, dy) in &directions {
-            let nx = x as isize + dx;
-            let ny = y as isize + dy;
+    let end = grid_size * grid_size - 1;
+    if corrupted[0] || corrupted[end] {
+        return None;
+    }
 
-            if nx >= 0 && ny >= 0 && nx < grid_size as isize && ny < grid_size as isize {
-                let next = (nx as usize, ny as usize);
+    let mut steps = vec![None; grid_size * grid_size];
+    let mut queue = VecDeque::from([0]);
+    steps[0] = Some(0);
 
-                if !corrupted.contains(&next) && !visited.contains(&next) {
-                    queue.push_back((next.0, next.1, steps + 1));
-                    visited.insert(next);
-                }
+    while let Some(position) = queue.pop_front() {
+        let distance = steps[position]?;
+        if position == end {
+            return Some(distance);
+        }
+
+        let (x, y) = (position % grid_size, position / grid_size);
+        let neighbors = [
+            (x > 0).then(|| position - 1),
+            (x + 1 < grid_size).then_some(position + 1),
+            (y > 0).then(|| position - grid_size),
+            (y + 1 < grid_size).then_some(position + grid_size),
+        ];
+        for next in neighbors.into_iter().flatten() {
+            if !corrupted[next] && steps[next].is_none() {
+                steps[next] = Some(distance + 1);
+                queue.push_back(next);
             }
         }
     }
@@ -57,67 +103,139 @@ fn find_shortest_path(byte_positions: &[(usize, usize)], blocks: usize) -> Optio
     None
 }
 
-pub fn start_offset_test_vs_prod(input_length: usize) -> usize {
-    if input_length > 100 {
-        1024
-    } else {
-        12
-    }
+/// Disjoint sets with path halving and union by size.
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let byte_positions = parse_input(input);
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+        }
+    }
 
-    let start_offset = start_offset_test_vs_prod(byte_positions.len());
+    fn find(&mut self, mut node: usize) -> usize {
+        while self.parent[node] != node {
+            self.parent[node] = self.parent[self.parent[node]];
+            node = self.parent[node];
+        }
+        node
+    }
 
-    find_shortest_path(&byte_positions, start_offset)
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
 }
 
-pub fn part_two(input: &str) -> Option<String> {
-    let byte_positions = parse_input(input);
-
-    let start_offset = if byte_positions.len() > 100 { 1024 } else { 12 };
-
-    let indexes: Vec<usize> = (start_offset..byte_positions.len()).collect();
+/// Index of the first byte that cuts the top left corner off from the bottom right one.
+///
+/// Bytes are added in order, joined to any fallen byte among their eight neighbours. The
+/// way is blocked as soon as one group of bytes reaches from the left or bottom edge to the
+/// top or right edge, each edge pair standing in as one extra node.
+pub fn first_blocking_byte(byte_positions: &[(usize, usize)], grid_size: usize) -> Option<usize> {
+    let cells = grid_size * grid_size;
+    let (left_bottom, top_right) = (cells, cells + 1);
+    let mut walls = UnionFind::new(cells + 2);
+    let mut corrupted = vec![false; cells];
+
+    for (i, &(x, y)) in byte_positions.iter().enumerate() {
+        let position = y * grid_size + x;
+        if corrupted[position] {
+            continue;
+        }
+        corrupted[position] = true;
 
-    let byte_pos_idx = indexes
-        .binary_search_by(|p| {
-            let index = p;
-            let first_result = find_shortest_path(&byte_positions, *index);
-            if first_result.is_none() {
-                let before = find_shortest_path(&byte_positions, index - 1);
-                if before.is_some() {
-                    // this is the iteration we want
-                    Ordering::Equal
-                } else {
-                    Ordering::Greater
+        if x == 0 || y + 1 == grid_size {
+            walls.union(position, left_bottom);
+        }
+        if y == 0 || x + 1 == grid_size {
+            walls.union(position, top_right);
+        }
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbor = x
+                    .checked_add_signed(dx)
+                    .zip(y.checked_add_signed(dy))
+                    .filter(|&(nx, ny)| nx < grid_size && ny < grid_size)
+                    .map(|(nx, ny)| ny * grid_size + nx)
+                    .filter(|&n| corrupted[n]);
+                if let Some(neighbor) = neighbor {
+                    walls.union(position, neighbor);
                 }
-            } else {
-                Ordering::Less
             }
-        })
-        .unwrap();
+        }
+
+        if walls.find(left_bottom) == walls.find(top_right) {
+            return Some(i);
+        }
+    }
 
-    // I offset this by 1024 to limit the search space, now it needs to be added back
-    // to find the actual index.
-    let byte_pos = byte_positions[byte_pos_idx + start_offset - 1];
+    None
+}
+
+pub fn part_one(input: &str) -> Result<usize, SolutionError> {
+    let memory = parse_input(input)?;
+
+    shortest_path(&memory.bytes, memory.grid_size, memory.fallen)
+        .ok_or_else(|| SolutionError::new("the exit is already cut off"))
+}
+
+pub fn part_two(input: &str) -> Result<String, SolutionError> {
+    let memory = parse_input(input)?;
 
-    Some(format!("{},{}", byte_pos.0, byte_pos.1))
+    let (x, y) = first_blocking_byte(&memory.bytes, memory.grid_size)
+        .map(|i| memory.bytes[i])
+        .ok_or_else(|| SolutionError::new("the exit is never cut off"))?;
+    Ok(format!("{x},{y}"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // the example's header makes it a 7x7 space with 12 bytes fallen for part one
+    fn example() -> Vec<(usize, usize)> {
+        parse_input(&advent_of_code::template::read_file("examples", DAY))
+            .unwrap()
+            .bytes
+    }
+
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(22));
+        assert_eq!(result, Ok(22));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some("6,1".to_string()));
+        assert_eq!(result, Ok("6,1".to_string()));
+    }
+
+    #[test]
+    fn test_blocking_matches_bfs() {
+        let bytes = example();
+        let blocking = first_blocking_byte(&bytes, 7).unwrap();
+        assert!(shortest_path(&bytes, 7, blocking).is_some());
+        assert!(shortest_path(&bytes, 7, blocking + 1).is_none());
+
+        // a byte on the start cuts it off straight away
+        assert_eq!(first_blocking_byte(&[(3, 3), (0, 0)], 7), Some(1));
+        assert_eq!(first_blocking_byte(&[(3, 3)], 7), None);
+        let error = parse_input("size 7, fallen 1\n1,1\n7,0\n").unwrap_err();
+        assert_eq!(error.location, Some((3, 1)));
+        assert!(parse_input("size 0, fallen 1\n").is_err());
+        assert_eq!(parse_input("70,70\n").unwrap().grid_size, GRID_SIZE);
     }
 }
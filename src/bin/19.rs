@@ -1,65 +1,191 @@
-use hashbrown::HashMap;
+use advent_of_code::template::SolutionError;
+use advent_of_code::visualize::flag_once;
 use rayon::prelude::*;
 advent_of_code::solution!(19);
 
-fn parse_input(input: &str) -> (Vec<&str>, Vec<&str>) {
+// marks a missing child, or a node where no towel ends
+const NONE: u32 = u32::MAX;
+
+fn parse_input(input: &str) -> Result<(Vec<&str>, Vec<&str>), SolutionError> {
     let mut iter = input.lines();
-    let towels: Vec<&str> = iter.next().unwrap().split(", ").collect();
-    iter.next();
+    let towels: Vec<&str> = iter
+        .next()
+        .ok_or_else(|| SolutionError::new("expected a line of towels"))?
+        .split(", ")
+        .collect();
+    if iter.next().is_some_and(|line| !line.is_empty()) {
+        return Err(SolutionError::at_line(
+            2,
+            1,
+            "expected a blank line after the towels",
+        ));
+    }
 
     let mut patterns = Vec::new();
     for line in iter {
         patterns.push(line);
     }
 
-    (towels, patterns)
+    Ok((towels, patterns))
 }
 
-fn has_valid_match(towels: &[&str], pattern: &str) -> bool {
-    towels
-        .iter()
-        .filter(|t| pattern.starts_with(*t))
-        .any(|prefix| pattern == *prefix || has_valid_match(towels, &pattern[prefix.len()..]))
+/// Every towel in one trie, so a pattern can be matched against all of them in one walk.
+///
+/// Only the colours that appear on towels get a slot in each node, so the child table stays
+/// a flat `Vec` indexed by `node * alphabet + colour`.
+#[derive(Debug, Clone)]
+pub struct TowelTrie<'a> {
+    towels: Vec<&'a str>,
+    // colour byte -> slot, or `NONE` for colours no towel has
+    slots: [u32; 256],
+    alphabet: usize,
+    children: Vec<u32>,
+    // the towel ending at each node
+    ends: Vec<u32>,
 }
 
-fn count_valid_match(towels: &[&str], pattern: &str, cache: &mut HashMap<String, usize>) -> usize {
-    if pattern.is_empty() {
-        return 1;
+impl<'a> TowelTrie<'a> {
+    pub fn new(towels: &[&'a str]) -> Self {
+        let mut slots = [NONE; 256];
+        let mut alphabet = 0;
+        for &b in towels.iter().flat_map(|t| t.as_bytes()) {
+            if slots[b as usize] == NONE {
+                slots[b as usize] = alphabet;
+                alphabet += 1;
+            }
+        }
+
+        let mut trie = Self {
+            towels: towels.to_vec(),
+            slots,
+            alphabet: alphabet as usize,
+            children: vec![NONE; alphabet as usize],
+            ends: vec![NONE],
+        };
+        for (id, towel) in towels.iter().enumerate() {
+            let mut node = 0;
+            for &b in towel.as_bytes() {
+                let child = trie.child_index(node, b).unwrap();
+                if trie.children[child] == NONE {
+                    trie.children[child] = trie.ends.len() as u32;
+                    trie.children
+                        .extend(std::iter::repeat_n(NONE, trie.alphabet));
+                    trie.ends.push(NONE);
+                }
+                node = trie.children[child] as usize;
+            }
+            // with repeated towels, the first one stands for all of them
+            if trie.ends[node] == NONE {
+                trie.ends[node] = id as u32;
+            }
+        }
+        trie
     }
-    if let Some(count) = cache.get(pattern) {
-        return *count;
+
+    fn child_index(&self, node: usize, colour: u8) -> Option<usize> {
+        match self.slots[colour as usize] {
+            NONE => None,
+            slot => Some(node * self.alphabet + slot as usize),
+        }
+    }
+
+    /// The towels that `pattern[start..]` begins with, as `(towel, end)` pairs, shortest first.
+    fn prefixes<'p>(
+        &'p self,
+        pattern: &'p [u8],
+        start: usize,
+    ) -> impl Iterator<Item = (u32, usize)> + 'p {
+        let mut node = 0;
+        pattern[start..]
+            .iter()
+            .enumerate()
+            .map_while(move |(i, &b)| {
+                let child = self.children[self.child_index(node, b)?];
+                if child == NONE {
+                    return None;
+                }
+                node = child as usize;
+                Some((self.ends[node], start + i + 1))
+            })
+            .filter(|&(towel, _)| towel != NONE)
     }
 
-    let count = towels
-        .iter()
-        .filter(|&&t| pattern.starts_with(t))
-        .map(|t| count_valid_match(towels, &pattern[t.len()..], cache))
-        .sum();
+    /// Counts the ways to lay out `pattern` with towels, or `None` if there are `usize::MAX`
+    /// or more. `ways[i]` is left holding the count for `pattern[i..]`, stopping at
+    /// `usize::MAX`, which still tells which suffixes can be made at all. Reusing `ways`
+    /// across calls saves allocating for every pattern.
+    pub fn count_arrangements(&self, pattern: &str, ways: &mut Vec<usize>) -> Option<usize> {
+        let pattern = pattern.as_bytes();
+        ways.clear();
+        ways.resize(pattern.len() + 1, 0);
+        ways[pattern.len()] = 1;
 
-    cache.insert(pattern.to_string(), count);
-    count
+        for start in (0..pattern.len()).rev() {
+            ways[start] = self
+                .prefixes(pattern, start)
+                .fold(0, |total, (_, end)| total.saturating_add(ways[end]));
+        }
+        (ways[0] != usize::MAX).then_some(ways[0])
+    }
+
+    /// One way to lay out `pattern`, using the counts [`TowelTrie::count_arrangements`] left
+    /// in `ways`, which must be for the same pattern.
+    pub fn decompose(&self, pattern: &str, ways: &[usize]) -> Option<Vec<&'a str>> {
+        let bytes = pattern.as_bytes();
+        if ways.len() != bytes.len() + 1 || ways[0] == 0 {
+            return None;
+        }
+
+        let mut towels = vec![];
+        let mut start = 0;
+        while start < bytes.len() {
+            let (towel, end) = self
+                .prefixes(bytes, start)
+                .find(|&(_, end)| ways[end] > 0)?;
+            towels.push(self.towels[towel as usize]);
+            start = end;
+        }
+        Some(towels)
+    }
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let (towels, patterns) = parse_input(input);
+// `--decompose` lists one way to make each pattern
+pub fn part_one(input: &str) -> Result<usize, SolutionError> {
+    let (towels, patterns) = parse_input(input)?;
+    let trie = TowelTrie::new(&towels);
+
+    if flag_once("--decompose") {
+        let mut ways = vec![];
+        for pattern in &patterns {
+            trie.count_arrangements(pattern, &mut ways);
+            match trie.decompose(pattern, &ways) {
+                Some(layout) => println!("{pattern}: {}", layout.join(", ")),
+                None => println!("{pattern}: impossible"),
+            }
+        }
+    }
 
-    Some(
-        patterns
-            .par_iter()
-            .filter(|p| has_valid_match(&towels, p))
-            .count(),
-    )
+    // too many ways to count is still a way
+    Ok(patterns
+        .par_iter()
+        .map_init(Vec::new, |ways, pattern| {
+            trie.count_arrangements(pattern, ways)
+        })
+        .filter(|&count| count != Some(0))
+        .count())
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let (towels, patterns) = parse_input(input);
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
+    let (towels, patterns) = parse_input(input)?;
+    let trie = TowelTrie::new(&towels);
 
-    Some(
-        patterns
-            .par_iter()
-            .map(|pattern| count_valid_match(&towels, pattern, &mut HashMap::new()))
-            .sum(),
-    )
+    patterns
+        .par_iter()
+        .map_init(Vec::new, |ways, pattern| {
+            trie.count_arrangements(pattern, ways)
+        })
+        .try_reduce(|| 0, usize::checked_add)
+        .ok_or_else(|| SolutionError::new("there are too many arrangements to count in a usize"))
 }
 
 #[cfg(test)]
@@ -69,12 +195,58 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(6));
+        assert_eq!(result, Ok(6));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(16));
+        assert_eq!(result, Ok(16));
+    }
+
+    #[test]
+    fn test_decompose() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let (towels, patterns) = parse_input(&input).unwrap();
+        let trie = TowelTrie::new(&towels);
+        let mut ways = vec![];
+
+        // from the puzzle text: gbbr can be made four ways, ubwu not at all
+        assert_eq!(trie.count_arrangements("gbbr", &mut ways), Some(4));
+        assert_eq!(trie.count_arrangements("ubwu", &mut ways), Some(0));
+        assert_eq!(trie.decompose("ubwu", &ways), None);
+        // a colour no towel has
+        assert_eq!(trie.count_arrangements("rxr", &mut ways), Some(0));
+
+        for pattern in patterns {
+            if trie.count_arrangements(pattern, &mut ways) != Some(0) {
+                let layout = trie.decompose(pattern, &ways).unwrap();
+                assert!(layout.iter().all(|t| towels.contains(t)));
+                assert_eq!(layout.concat(), pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn test_too_many_arrangements() {
+        // 100 stripes can be laid out with 1 and 2 stripe towels in a Fibonacci number of
+        // ways, well past a usize
+        let input = format!("w, ww\n\n{}\nr\n", "w".repeat(100));
+        assert_eq!(part_one(&input), Ok(1));
+        assert!(part_two(&input).is_err());
+
+        let trie = TowelTrie::new(&["w", "ww"]);
+        let mut ways = vec![];
+        assert_eq!(trie.count_arrangements(&"w".repeat(100), &mut ways), None);
+        assert_eq!(
+            trie.count_arrangements(&"w".repeat(10), &mut ways),
+            Some(89)
+        );
+        // a pattern that can't start doesn't count its long tail
+        let pattern = format!("r{}", "w".repeat(100));
+        assert_eq!(trie.count_arrangements(&pattern, &mut ways), Some(0));
+
+        assert!(part_one("").is_err());
+        assert!(part_one("w\nw\n").is_err());
     }
 }
@@ -1,6 +1,9 @@
 advent_of_code::solution!(20);
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+
+use advent_of_code::template::{SolutionError, arg_value};
+use advent_of_code::visualize::{Palette, Visualize, Visualizer, flag_once};
 
 // TODO: Clean up this implementation
 
@@ -20,7 +23,21 @@ pub enum GridTile {
     Wall,
 }
 
+// the tiles next to `position`, staying inside the grid
+fn neighbors(position: usize, width: usize, len: usize) -> impl Iterator<Item = usize> {
+    let x = position % width;
+    [
+        (x + 1 < width).then(|| position + 1),
+        (x > 0).then(|| position - 1),
+        (position + width < len).then(|| position + width),
+        position.checked_sub(width),
+    ]
+    .into_iter()
+    .flatten()
+}
+
 fn find_original_min_distances(tiles: &mut [GridTile], width: usize, start: usize, end: usize) {
+    let len = tiles.len();
     let mut queue = VecDeque::new();
     queue.push_front((start, 0));
 
@@ -29,14 +46,8 @@ fn find_original_min_distances(tiles: &mut [GridTile], width: usize, start: usiz
             && distances.0.is_none()
         {
             distances.0 = Some(distance);
-            [
-                position + 1,
-                position - 1,
-                position + width,
-                position - width,
-            ]
-            .into_iter()
-            .for_each(|new_position| queue.push_back((new_position, distance + 1)));
+            neighbors(position, width, len)
+                .for_each(|new_position| queue.push_back((new_position, distance + 1)));
         }
 
         if position == end {
@@ -54,14 +65,8 @@ fn find_original_min_distances(tiles: &mut [GridTile], width: usize, start: usiz
             && distances.1.is_none()
         {
             distances.1 = Some(distance);
-            [
-                position + 1,
-                position - 1,
-                position + width,
-                position - width,
-            ]
-            .into_iter()
-            .for_each(|new_position| queue.push_back((new_position, distance + 1)));
+            neighbors(position, width, len)
+                .for_each(|new_position| queue.push_back((new_position, distance + 1)));
         }
 
         if position == end {
@@ -78,169 +83,233 @@ pub struct Map {
     pub original_distance: usize,
 }
 
+// offsets within `reach` steps, ignoring walls, generated as they're needed so each tile of
+// the track doesn't need its own copy
+fn diamond(reach: isize) -> impl Iterator<Item = (isize, isize)> {
+    (-reach..=reach).flat_map(move |dy| {
+        let width = reach - dy.abs();
+        (-width..=width).map(move |dx| (dx, dy))
+    })
+}
+
 impl Map {
-    fn parse_input(input: &str) -> Self {
-        let width = input
-            .lines()
-            .next()
-            .unwrap()
-            .chars()
-            .collect::<Vec<_>>()
-            .len();
-        let height = input.lines().collect::<Vec<_>>().len();
+    fn parse_input(input: &str) -> Result<Self, SolutionError> {
+        let width = input.lines().next().map_or(0, str::len);
+        let height = input.lines().count();
         let mut start: Option<usize> = None;
         let mut end: Option<usize> = None;
-        let mut tiles: Vec<GridTile> = input
-            .chars()
-            .filter(|&c| c != '\n')
-            .enumerate()
-            .map(|(n, c)| match c {
-                '#' => GridTile::Wall,
-                '.' => GridTile::Open(Distances(None, None)),
-                'S' => {
-                    start = Some(n);
-                    GridTile::Open(Distances(None, None))
-                }
-                'E' => {
-                    end = Some(n);
-                    GridTile::Open(Distances(None, None))
-                }
-                _ => unreachable!("Unrecognized character: {}", c),
-            })
-            .collect();
+        let mut tiles: Vec<GridTile> = Vec::with_capacity(width * height);
+
+        for (y, line) in input.lines().enumerate() {
+            if line.len() != width {
+                return Err(SolutionError::at_line(
+                    y + 1,
+                    1,
+                    format!("expected a row {width} tiles wide"),
+                ));
+            }
+            for (x, c) in line.chars().enumerate() {
+                let tile = match c {
+                    '#' => GridTile::Wall,
+                    '.' => GridTile::Open(Distances(None, None)),
+                    'S' => {
+                        start = Some(tiles.len());
+                        GridTile::Open(Distances(None, None))
+                    }
+                    'E' => {
+                        end = Some(tiles.len());
+                        GridTile::Open(Distances(None, None))
+                    }
+                    _ => {
+                        return Err(SolutionError::at_line(
+                            y + 1,
+                            x + 1,
+                            format!("unexpected tile {c:?}"),
+                        ));
+                    }
+                };
+                tiles.push(tile);
+            }
+        }
 
-        let start = start.expect("No start value found");
-        let end = end.expect("No end value found");
+        let start = start.ok_or_else(|| SolutionError::new("the track has no start tile"))?;
+        let end = end.ok_or_else(|| SolutionError::new("the track has no end tile"))?;
 
         find_original_min_distances(&mut tiles, width, start, end);
 
-        let best_distance = match tiles[end] {
-            GridTile::Open(Distances(Some(dist), _)) => dist,
-            _ => unreachable!("No path reached the goal"),
+        let GridTile::Open(Distances(Some(best_distance), _)) = tiles[end] else {
+            return Err(SolutionError::new(
+                "the track doesn't lead from the start to the end",
+            ));
         };
 
-        Map {
+        Ok(Map {
             tiles,
             height,
             width,
             original_distance: best_distance,
-        }
+        })
+    }
+
+    /// Every cheat lasting at most `max_duration` picoseconds that saves at least
+    /// `min_saving`, and always at least one.
+    pub fn cheats(&self, max_duration: usize, min_saving: usize) -> impl Iterator<Item = Cheat> {
+        let min_saving = min_saving.max(1);
+        let reach = max_duration as isize;
+
+        self.track().flat_map(move |(start, from_start)| {
+            diamond(reach).filter_map(move |(dx, dy)| {
+                let end_x = start.0.checked_add_signed(dx).filter(|&x| x < self.width)?;
+                let end_y = start
+                    .1
+                    .checked_add_signed(dy)
+                    .filter(|&y| y < self.height)?;
+                let GridTile::Open(Distances(_, Some(to_end))) =
+                    self.tiles[end_y * self.width + end_x]
+                else {
+                    return None;
+                };
+                let length = dx.unsigned_abs() + dy.unsigned_abs();
+                let saving = self
+                    .original_distance
+                    .checked_sub(from_start + length + to_end)?;
+                (saving >= min_saving).then_some(Cheat {
+                    start,
+                    end: (end_x, end_y),
+                    length,
+                    saving,
+                })
+            })
+        })
+    }
+
+    // `((x, y), distance from the start)` of every tile on the track
+    fn track(&self) -> impl Iterator<Item = ((usize, usize), usize)> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(position, tile)| match tile {
+                GridTile::Open(Distances(Some(from_start), _)) => {
+                    Some(((position % self.width, position / self.width), *from_start))
+                }
+                _ => None,
+            })
     }
+}
 
-    fn len(&self) -> usize {
-        self.tiles.len()
+/// A shortcut from one track tile to another, through whatever is in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cheat {
+    /// `(x, y)` of the track tile the cheat starts on.
+    pub start: (usize, usize),
+    /// `(x, y)` of the track tile the cheat ends on.
+    pub end: (usize, usize),
+    /// Picoseconds spent cheating.
+    pub length: usize,
+    /// Picoseconds saved over the race without cheats.
+    pub saving: usize,
+}
+
+/// How many cheats save each amount of time.
+pub fn savings_histogram(cheats: impl IntoIterator<Item = Cheat>) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for cheat in cheats {
+        *histogram.entry(cheat.saving).or_insert(0) += 1;
     }
+    histogram
+}
 
-    fn check_for_cheats(&self, x: usize, y: usize, save_distance: usize) -> Option<usize> {
-        let tiles = &self.tiles;
-        let position = self.width * y + x;
-        if let GridTile::Wall = &tiles[position] {
-            let neighbors = [
-                &tiles[position + 1],
-                &tiles[position - 1],
-                &tiles[position + self.width],
-                &tiles[position - self.width],
-            ];
-
-            Some(
-                neighbors
-                    .iter()
-                    .flat_map(|a| {
-                        neighbors.iter().filter(move |b| {
-                            if let (
-                                GridTile::Open(Distances(Some(start_distance), _)),
-                                GridTile::Open(Distances(_, Some(end_distance))),
-                            ) = (*a, **b)
-                            {
-                                start_distance + end_distance + 2
-                                    <= self.original_distance - save_distance
-                            } else {
-                                false
-                            }
-                        })
-                    })
-                    .count(),
-            )
+// lists the histogram the way the puzzle text does
+fn print_histogram(histogram: &BTreeMap<usize, usize>) {
+    for (&saving, &count) in histogram {
+        if count == 1 {
+            println!("There is one cheat that saves {saving} picoseconds.");
         } else {
-            None
+            println!("There are {count} cheats that save {saving} picoseconds.");
         }
     }
-    fn cheat_count(&self) -> usize {
-        let minimum_cheat_distance = if self.width > 20 { 100 } else { 2 };
-
-        let end_position = (self.len() / self.width) - 1;
-        (1..end_position)
-            .flat_map(|y| {
-                let end_pos = self.width - 1;
-                (1..end_pos)
-                    .map(move |x| (x, y, minimum_cheat_distance))
-                    .filter_map(|(x, y, save_distance)| self.check_for_cheats(x, y, save_distance))
-            })
-            .sum()
+}
+
+// Counts the cheats of up to `max_duration` that save at least `min_saving`.
+// `--max-cheat-<part> <n>` and `--min-saving-<part> <n>` replace either for that part only,
+// and `--cheats` prints the savings histogram.
+#[track_caller]
+fn count_cheats(
+    map: &Map,
+    part: u8,
+    max_duration: usize,
+    min_saving: usize,
+) -> Result<usize, SolutionError> {
+    let max_duration = arg_value(&format!("--max-cheat-{part}"))?.unwrap_or(max_duration);
+    let min_saving = arg_value(&format!("--min-saving-{part}"))?.unwrap_or(min_saving);
+
+    if flag_once("--cheats") {
+        let histogram = savings_histogram(map.cheats(max_duration, min_saving));
+        print_histogram(&histogram);
+        Ok(histogram.values().sum())
+    } else {
+        Ok(map.cheats(max_duration, min_saving).count())
     }
+}
 
-    pub fn find_cheats_from_position(
-        &self,
-        x: usize,
-        y: usize,
-        cheat_distance: usize,
-        save_distance: usize,
-    ) -> Option<usize> {
-        let pos = y * self.width + x;
-        if let GridTile::Open(Distances(Some(distance_from_start), _)) = self.tiles[pos] {
-            let check_start = x.max(cheat_distance + 1) - cheat_distance;
-            let check_end = (x + cheat_distance).min(self.width - 2);
-            Some(
-                (check_start..=check_end)
-                    .flat_map(|x_offset| {
-                        let x_distance = x.abs_diff(x_offset);
-                        let max_y_distance = cheat_distance - x_distance;
-                        let check_start = y.max(max_y_distance + 1) - max_y_distance;
-                        let check_end = (y + max_y_distance).min(self.height - 2);
-                        (check_start..=check_end).filter(move |y_offset| {
-                            let other_position = *y_offset * self.width + x_offset;
-                            if let GridTile::Open(Distances(_, Some(distance_from_end))) =
-                                self.tiles[other_position]
-                            {
-                                distance_from_start
-                                    + distance_from_end
-                                    + x_distance
-                                    + y.abs_diff(*y_offset)
-                                    <= self.original_distance - save_distance
-                            } else {
-                                false
-                            }
-                        })
-                    })
-                    .count(),
-            )
-        } else {
-            None
+/// The track with every tile up to `reached` steps from the start lit up, brighter further along.
+pub struct RaceProgress<'a> {
+    pub map: &'a Map,
+    pub reached: usize,
+}
+
+impl Visualize for RaceProgress<'_> {
+    // walls are `None`, track tiles are their distance band, 0 being unreached
+    type Cell = Option<u8>;
+
+    fn size(&self) -> (usize, usize) {
+        (self.map.width, self.map.height)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Option<u8> {
+        match self.map.tiles[y * self.map.width + x] {
+            GridTile::Wall => None,
+            GridTile::Open(Distances(Some(d), _)) if d <= self.reached => {
+                Some(1 + (d * 4 / (self.map.original_distance + 1)) as u8)
+            }
+            GridTile::Open(_) => Some(0),
         }
     }
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let map = Map::parse_input(input);
+// draws the track filling in from the start, if `--visualize` was passed
+fn visualize_race(map: &Map) {
+    let palette = Palette::new('#', [60, 60, 70])
+        .with(Some(0), '.', [20, 20, 25])
+        .with(Some(1), 'o', [70, 120, 230])
+        .with(Some(2), 'o', [90, 200, 200])
+        .with(Some(3), 'o', [140, 220, 90])
+        .with(Some(4), 'o', [240, 200, 60]);
+    let Some(mut visualizer) = Visualizer::from_args(DAY, palette) else {
+        return;
+    };
+    for reached in 0..=map.original_distance {
+        visualizer.frame(&RaceProgress { map, reached });
+    }
+    visualizer.finish(&RaceProgress {
+        map,
+        reached: map.original_distance,
+    });
+}
+
+pub fn part_one(input: &str) -> Result<usize, SolutionError> {
+    let map = Map::parse_input(input)?;
+    visualize_race(&map);
 
-    Some(map.cheat_count())
+    let min_saving = if map.width > 20 { 100 } else { 2 };
+    count_cheats(&map, 1, 2, min_saving)
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let map = Map::parse_input(input);
-    let save_distance = if map.width < 20 { 50 } else { 100 };
-    let max_cheat = 20;
-    let height = map.height - 1;
-
-    let result = (1..height)
-        .flat_map(|y| {
-            (1..map.width - 1)
-                .map(move |x| (x, y))
-                .filter_map(|(x, y)| map.find_cheats_from_position(x, y, max_cheat, save_distance))
-        })
-        .sum();
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
+    let map = Map::parse_input(input)?;
 
-    Some(result)
+    let min_saving = if map.width > 20 { 100 } else { 50 };
+    count_cheats(&map, 2, 20, min_saving)
 }
 
 #[cfg(test)]
@@ -250,12 +319,90 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(44));
+        assert_eq!(result, Ok(44));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(285));
+        assert_eq!(result, Ok(285));
+    }
+
+    #[test]
+    fn test_histograms() {
+        let map = Map::parse_input(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+
+        let expected = [
+            (2, 14),
+            (4, 14),
+            (6, 2),
+            (8, 4),
+            (10, 2),
+            (12, 3),
+            (20, 1),
+            (36, 1),
+            (38, 1),
+            (40, 1),
+            (64, 1),
+        ];
+        assert_eq!(
+            savings_histogram(map.cheats(2, 1)),
+            BTreeMap::from(expected)
+        );
+
+        let expected = [
+            (50, 32),
+            (52, 31),
+            (54, 29),
+            (56, 39),
+            (58, 25),
+            (60, 23),
+            (62, 20),
+            (64, 19),
+            (66, 12),
+            (68, 14),
+            (70, 12),
+            (72, 22),
+            (74, 4),
+            (76, 3),
+        ];
+        assert_eq!(
+            savings_histogram(map.cheats(20, 50)),
+            BTreeMap::from(expected)
+        );
+    }
+
+    #[test]
+    fn test_cheat_details() {
+        let map = Map::parse_input(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+
+        // the best two picosecond cheat, through the wall next to the end
+        let best = map.cheats(2, 64).collect::<Vec<_>>();
+        assert_eq!(
+            best,
+            vec![Cheat {
+                start: (7, 7),
+                end: (5, 7),
+                length: 2,
+                saving: 64,
+            }]
+        );
+
+        assert!(map.cheats(6, 1).all(|c| (1..=6).contains(&c.length)));
+    }
+
+    #[test]
+    fn test_bad_tracks() {
+        let error = part_one("#####\n#S.E#\n#.x.#\n#####\n").unwrap_err();
+        assert_eq!(error.location, Some((3, 3)));
+        assert_eq!(error.message, "unexpected tile 'x'");
+
+        assert!(part_one("").is_err());
+        assert!(part_one("#####\n#S..#\n#####\n").is_err());
+        let error = part_two("#####\n#S#E#\n#####\n").unwrap_err();
+        assert_eq!(
+            error.message,
+            "the track doesn't lead from the start to the end"
+        );
     }
 }
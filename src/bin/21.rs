@@ -1,141 +1,264 @@
-use cached::proc_macro::cached;
-
 advent_of_code::solution!(21);
 
-fn number_pad(key: char) -> (i32, i32) {
-    match key {
-        '7' => (0, 0),
-        '8' => (1, 0),
-        '9' => (2, 0),
-        '4' => (0, 1),
-        '5' => (1, 1),
-        '6' => (2, 1),
-        '1' => (0, 2),
-        '2' => (1, 2),
-        '3' => (2, 2),
-        '0' => (1, 3),
-        'A' => (2, 3),
-        _ => unreachable!("Invalid character {key}"),
-    }
+use advent_of_code::template::SolutionError;
+use hashbrown::HashMap;
+use pathfinding::directed::dijkstra::dijkstra;
+
+pub const NUMERIC_KEYPAD: [&str; 4] = ["789", "456", "123", " 0A"];
+pub const DIRECTIONAL_KEYPAD: [&str; 2] = [" ^A", "<v>"];
+
+// the keys a keypad needs to steer the arm of another
+const DIRECTION_KEYS: [char; 5] = ['^', 'v', '<', '>', 'A'];
+
+/// A grid of keys. Every arm starts over `A`, and must never point at a gap.
+#[derive(Debug, Clone)]
+pub struct Keypad {
+    rows: Vec<Vec<Option<char>>>,
+    positions: HashMap<char, (usize, usize)>,
 }
 
-fn directional_pad(key: char) -> (i32, i32) {
-    match key {
-        '^' => (1, 0),
-        'A' => (2, 0),
-        '<' => (0, 1),
-        'v' => (1, 1),
-        '>' => (2, 1),
-        _ => unreachable!("Invalid character {key}"),
+impl Keypad {
+    /// Builds a keypad from its rows, with a space for each gap. Every key has to be reachable
+    /// from `A`.
+    pub fn new(rows: &[&str]) -> Result<Self, SolutionError> {
+        let rows: Vec<Vec<Option<char>>> = rows
+            .iter()
+            .map(|row| row.chars().map(|c| (c != ' ').then_some(c)).collect())
+            .collect();
+
+        let mut positions = HashMap::new();
+        for (y, row) in rows.iter().enumerate() {
+            let keys = row
+                .iter()
+                .enumerate()
+                .filter_map(|(x, &key)| Some((x, key?)));
+            for (x, key) in keys {
+                if positions.insert(key, (x, y)).is_some() {
+                    return Err(SolutionError::new(format!(
+                        "key {key:?} appears twice, the second time in keypad row {}",
+                        y + 1
+                    )));
+                }
+            }
+        }
+        if !positions.contains_key(&'A') {
+            return Err(SolutionError::new("a keypad needs an A key to start on"));
+        }
+
+        let pad = Self { rows, positions };
+        let mut reached = vec!['A'];
+        let mut i = 0;
+        while let Some(&key) = reached.get(i) {
+            for direction in ['^', 'v', '<', '>'] {
+                match pad.neighbor(key, direction) {
+                    Some(next) if !reached.contains(&next) => reached.push(next),
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        if let Some(key) = pad.keys().find(|key| !reached.contains(key)) {
+            return Err(SolutionError::new(format!(
+                "key {key:?} can't be reached from A without crossing a gap"
+            )));
+        }
+
+        Ok(pad)
     }
-}
 
-#[cached]
-fn find_arrow_sequence(
-    x: i32,
-    y: i32,
-    recursion_depth: usize,
-    check_horizontal_first: bool,
-) -> usize {
-    let (x_offset, y_offset) = (x.unsigned_abs(), y.unsigned_abs());
-    let mut directions_to_check: Vec<char> = Vec::new();
-    if x > 0 {
-        (0..x_offset).for_each(|_| {
-            directions_to_check.push('<');
-        });
-    } else {
-        (0..x_offset).for_each(|_| {
-            directions_to_check.push('>');
-        });
+    pub fn has_key(&self, key: char) -> bool {
+        self.positions.contains_key(&key)
     }
-    if y > 0 {
-        (0..y_offset).for_each(|_| {
-            directions_to_check.push('^');
-        });
-    } else {
-        (0..y_offset).for_each(|_| {
-            directions_to_check.push('v');
-        });
+
+    pub fn keys(&self) -> impl Iterator<Item = char> + '_ {
+        self.positions.keys().copied()
     }
 
-    if !check_horizontal_first {
-        directions_to_check.reverse();
+    fn key_at(&self, x: usize, y: usize) -> Option<char> {
+        *self.rows.get(y)?.get(x)?
     }
 
-    directions_to_check.push('A');
-
-    if recursion_depth == 0 {
-        directions_to_check.len()
-    } else {
-        let recursion_depth = recursion_depth - 1;
-        let mut current_location = directional_pad('A');
-
-        directions_to_check
-            .into_iter()
-            .map(|c| {
-                let needed = directional_pad(c);
-                let point = current_location;
-                current_location = needed;
-                let offset = (point.0 - needed.0, point.1 - needed.1);
-                if offset.0 == 0 || offset.1 == 0 || (needed == (0, 1) && point.1 == 0) {
-                    find_arrow_sequence(offset.0, offset.1, recursion_depth, false)
-                } else if point == (0, 1) && needed.1 == 0 {
-                    find_arrow_sequence(offset.0, offset.1, recursion_depth, true)
-                } else {
-                    find_arrow_sequence(offset.0, offset.1, recursion_depth, false).min(
-                        find_arrow_sequence(offset.0, offset.1, recursion_depth, true),
-                    )
-                }
-            })
-            .sum()
+    // the key reached by pressing `direction` with the arm over `key`
+    fn neighbor(&self, key: char, direction: char) -> Option<char> {
+        let (x, y) = self.positions[&key];
+        match direction {
+            '^' => self.key_at(x, y.checked_sub(1)?),
+            'v' => self.key_at(x, y + 1),
+            '<' => self.key_at(x.checked_sub(1)?, y),
+            '>' => self.key_at(x + 1, y),
+            _ => None,
+        }
+    }
+
+    /// Replays `presses` on the directional keypad that drives this one, returning the keys
+    /// typed here. Fails if the arm is sent into a gap or off the keypad.
+    pub fn operate(&self, presses: &str) -> Result<String, SolutionError> {
+        let mut arm = 'A';
+        let mut typed = String::new();
+        for (i, press) in presses.chars().enumerate() {
+            if press == 'A' {
+                typed.push(arm);
+                continue;
+            }
+            arm = self.neighbor(arm, press).ok_or_else(|| {
+                SolutionError::new(format!(
+                    "press {i} ({press:?}) moves the arm off {arm:?} into a gap"
+                ))
+            })?;
+        }
+        Ok(typed)
     }
 }
 
-fn solve_passcode(passcode: &str, recursion_depth: usize) -> usize {
-    let mut current_location = number_pad('A');
-
-    // remove trailing A: 029A -> 029 -> 29
-    let n = passcode[0..3].parse::<usize>().unwrap();
-
-    n * passcode
-        .chars()
-        .map(|ch| {
-            let needed = number_pad(ch);
-            let point = current_location;
-            let offset = (current_location.0 - needed.0, current_location.1 - needed.1);
-            current_location = needed;
-            // prefer up > right > other
-            if point.1 == 3 && needed.0 == 0 {
-                find_arrow_sequence(offset.0, offset.1, recursion_depth, false)
-            } else if point.1 == 0 && needed.0 == 3 {
-                find_arrow_sequence(offset.0, offset.1, recursion_depth, true)
-            } else {
-                find_arrow_sequence(offset.0, offset.1, recursion_depth, true).min(
-                    find_arrow_sequence(offset.0, offset.1, recursion_depth, false),
-                )
+/// Keypads driving one another: each pad's arm is steered by the pad after it, and the last
+/// pad is pressed by hand.
+#[derive(Debug, Clone)]
+pub struct KeypadChain {
+    pads: Vec<Keypad>,
+    // for each pad but the last, the cheapest way to get from one key to pressing another:
+    // the hand presses it costs, and what to type on the next pad to do it
+    routes: Vec<HashMap<(char, char), (usize, String)>>,
+}
+
+impl KeypadChain {
+    pub fn new(pads: Vec<Keypad>) -> Result<Self, SolutionError> {
+        if pads.is_empty() {
+            return Err(SolutionError::new(
+                "a keypad chain needs at least one keypad",
+            ));
+        }
+        if let Some(i) =
+            (1..pads.len()).find(|&i| !DIRECTION_KEYS.iter().all(|&k| pads[i].has_key(k)))
+        {
+            return Err(SolutionError::new(format!(
+                "keypad {i} steers another, so it needs ^, v, <, > and A"
+            )));
+        }
+
+        // work back from the hand, each level only needing the costs of the one after it
+        let mut routes: Vec<HashMap<(char, char), (usize, String)>> = vec![];
+        for level in (0..pads.len() - 1).rev() {
+            let next = routes.last();
+            let cost = |from: char, to: char| next.map_or(1, |r| r[&(from, to)].0);
+
+            let mut table = HashMap::new();
+            for from in pads[level].keys() {
+                for to in pads[level].keys() {
+                    table.insert((from, to), cheapest_route(&pads[level], from, to, cost));
+                }
             }
-        })
-        .sum::<usize>()
+            routes.push(table);
+        }
+        routes.reverse();
+
+        Ok(Self { pads, routes })
+    }
+
+    /// A numeric keypad behind `robots` directional keypads, with one more pressed by hand.
+    pub fn door(robots: usize) -> Self {
+        let mut pads = vec![Keypad::new(&NUMERIC_KEYPAD).unwrap()];
+        pads.extend((0..=robots).map(|_| Keypad::new(&DIRECTIONAL_KEYPAD).unwrap()));
+        Self::new(pads).unwrap()
+    }
+
+    fn check_code(&self, code: &str) -> Result<(), SolutionError> {
+        match code.chars().find(|&c| !self.pads[0].has_key(c)) {
+            Some(c) => Err(SolutionError::new(format!(
+                "{code:?} has {c:?}, which isn't on the keypad"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The fewest hand presses that type `code` on the first keypad.
+    pub fn presses(&self, code: &str) -> Result<usize, SolutionError> {
+        self.check_code(code)?;
+        let Some(routes) = self.routes.first() else {
+            return Ok(code.chars().count());
+        };
+        Ok(pairs(code).map(|pair| routes[&pair].0).sum())
+    }
+
+    /// One optimal way to type `code`: what gets typed on each keypad, starting with `code`
+    /// itself and ending with the hand presses. These grow exponentially along the chain,
+    /// so keep it short.
+    pub fn sequences(&self, code: &str) -> Result<Vec<String>, SolutionError> {
+        self.check_code(code)?;
+        let mut sequences = vec![code.to_string()];
+        for routes in &self.routes {
+            let typed = sequences.last().unwrap();
+            let next = pairs(typed).map(|pair| routes[&pair].1.as_str()).collect();
+            sequences.push(next);
+        }
+        Ok(sequences)
+    }
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    Some(
-        input
-            .lines()
-            .map(|passcode| solve_passcode(passcode, 2))
-            .sum(),
-    )
+// consecutive keys of `typed`, starting from the A every arm rests on
+fn pairs(typed: &str) -> impl Iterator<Item = (char, char)> + '_ {
+    std::iter::once('A').chain(typed.chars()).zip(typed.chars())
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    Some(
-        input
-            .lines()
-            .map(|passcode| solve_passcode(passcode, 25))
-            .sum(),
+// Searches over (arm on this pad, key last pressed on the pad steering it), so a move costs
+// whatever the steering pad needs to get from its last key to the direction pressed. The route
+// needn't be the shortest on this pad, and only ever passes over keys.
+fn cheapest_route(
+    pad: &Keypad,
+    from: char,
+    to: char,
+    cost: impl Fn(char, char) -> usize,
+) -> (usize, String) {
+    // the third field marks the final press of `to`
+    let (path, total) = dijkstra(
+        &(from, 'A', false),
+        |&(arm, steering, pressed)| {
+            let mut next = vec![];
+            if pressed {
+                return next;
+            }
+            if arm == to {
+                next.push(((arm, 'A', true), cost(steering, 'A')));
+            }
+            for direction in ['^', 'v', '<', '>'] {
+                if let Some(key) = pad.neighbor(arm, direction) {
+                    next.push(((key, direction, false), cost(steering, direction)));
+                }
+            }
+            next
+        },
+        |&(_, _, pressed)| pressed,
+    )
+    .expect("Keypad::new checks every key can be reached");
+
+    (
+        total,
+        path[1..].iter().map(|&(_, steering, _)| steering).collect(),
     )
 }
 
+// the code's number, ignoring the A at the end: 029A -> 29
+fn numeric_part(code: &str) -> Result<usize, SolutionError> {
+    code.trim_end_matches('A')
+        .parse()
+        .map_err(|_| SolutionError::new(format!("{code:?} isn't a number followed by A")))
+}
+
+fn complexity_sum(input: &str, robots: usize) -> Result<usize, SolutionError> {
+    let chain = KeypadChain::door(robots);
+    input
+        .lines()
+        .map(|code| Ok(chain.presses(code)? * numeric_part(code)?))
+        .sum()
+}
+
+pub fn part_one(input: &str) -> Result<usize, SolutionError> {
+    complexity_sum(input, 2)
+}
+
+pub fn part_two(input: &str) -> Result<usize, SolutionError> {
+    complexity_sum(input, 25)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,12 +266,51 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(126384));
+        assert_eq!(result, Ok(126384));
     }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(154115708116294));
+        assert_eq!(result, Ok(154115708116294));
+    }
+
+    #[test]
+    fn test_sequences() {
+        let chain = KeypadChain::door(2);
+        let sequences = chain.sequences("029A").unwrap();
+
+        // lengths from the puzzle walkthrough
+        let lengths: Vec<usize> = sequences.iter().map(String::len).collect();
+        assert_eq!(lengths, vec![4, 12, 28, 68]);
+        assert_eq!(chain.presses("029A"), Ok(68));
+
+        // each layer types the one before it, never crossing a gap
+        for (pad, pair) in chain.pads.iter().zip(sequences.windows(2)) {
+            assert_eq!(pad.operate(&pair[1]).as_deref(), Ok(pair[0].as_str()));
+        }
+    }
+
+    #[test]
+    fn test_custom_keypads() {
+        // a numeric pad with a gap in the middle, which routes have to go around
+        let ring = Keypad::new(&["123", "4 5", "67A"]).unwrap();
+        assert!(ring.operate("^<").is_err());
+
+        let directional = Keypad::new(&DIRECTIONAL_KEYPAD).unwrap();
+        let chain = KeypadChain::new(vec![ring.clone(), directional.clone(), directional]).unwrap();
+        let sequences = chain.sequences("2A7").unwrap();
+        assert_eq!(ring.operate(&sequences[1]).as_deref(), Ok("2A7"));
+        assert_eq!(chain.presses("2A7"), Ok(sequences[2].len()));
+
+        assert!(KeypadChain::new(vec![ring.clone(), ring]).is_err());
+        assert!(chain.presses("9A").is_err());
+        let error = Keypad::new(&["12", "21", " A"]).unwrap_err();
+        assert_eq!(error.location, None);
+        assert_eq!(
+            error.message,
+            "key '2' appears twice, the second time in keypad row 2"
+        );
+        assert!(Keypad::new(&["A 1"]).is_err());
     }
 }
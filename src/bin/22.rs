@@ -1,10 +1,8 @@
 advent_of_code::solution!(22);
 
+use advent_of_code::visualize::flag_once;
 use atoi_simd::parse;
-use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
-
-use nohash::BuildNoHashHasher;
 use rayon::prelude::*;
 
 const ITERATIONS: usize = 2000;
@@ -59,15 +57,93 @@ fn changes(secret: u64, iters: usize) -> impl Iterator<Item = u8> {
     }))
 }
 
-/// Builds a key for the HashMap given the different prices. Calculate the differences,
-/// and use them to build the key. Squash them all into a u32
-fn build_key(a: u8, b: u8, c: u8, d: u8, e: u8) -> u32 {
-    let d1 = ((b as i8 - a as i8) + 10) as u8;
-    let d2 = ((c as i8 - b as i8) + 10) as u8;
-    let d3 = ((d as i8 - c as i8) + 10) as u8;
-    let d4 = ((e as i8 - d as i8) + 10) as u8;
+/// Number of possible runs of four price changes, each from -9 to 9.
+const CHANGE_SPACE: usize = 19 * 19 * 19 * 19;
+
+/// Index into the change space of the four changes between five prices, read as a
+/// base 19 number with each change offset by 9
+#[inline]
+fn change_index(a: u8, b: u8, c: u8, d: u8, e: u8) -> usize {
+    [(a, b), (b, c), (c, d), (d, e)]
+        .into_iter()
+        .fold(0, |index, (from, to)| index * 19 + (to + 9 - from) as usize)
+}
+
+/// The four price changes that `index` stands for
+fn change_sequence(index: usize) -> [i8; 4] {
+    let mut sequence = [0; 4];
+    let mut rest = index;
+    for change in sequence.iter_mut().rev() {
+        *change = (rest % 19) as i8 - 9;
+        rest /= 19;
+    }
+    sequence
+}
+
+/// The run of price changes the monkey should wait for, and the bananas it brings in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BestSequence {
+    pub changes: [i8; 4],
+    pub bananas: u32,
+}
+
+/// Finds the change sequence that earns the most bananas across all buyers. Totals live in
+/// one flat array over the change space, and each buyer only counts the first time a
+/// sequence shows up, tracked by stamping entries with the buyer's number.
+pub fn best_sequence(secrets: &[u64]) -> Option<BestSequence> {
+    let totals = secrets
+        .par_iter()
+        .fold(
+            || (vec![0u32; CHANGE_SPACE], vec![0u32; CHANGE_SPACE], 0),
+            |(mut totals, mut seen, mut buyer), &secret| {
+                buyer += 1;
+                for (a, b, c, d, e) in changes(secret, ITERATIONS).tuple_windows() {
+                    let index = change_index(a, b, c, d, e);
+                    if seen[index] != buyer {
+                        seen[index] = buyer;
+                        totals[index] += e as u32;
+                    }
+                }
+                (totals, seen, buyer)
+            },
+        )
+        .map(|(totals, _, _)| totals)
+        .reduce_with(|mut a, b| {
+            a.iter_mut()
+                .zip(b)
+                .for_each(|(total, other)| *total += other);
+            a
+        })?;
 
-    u32::from_ne_bytes([d1, d2, d3, d4])
+    let (index, &bananas) = totals.iter().enumerate().max_by_key(|&(_, total)| total)?;
+    Some(BestSequence {
+        changes: change_sequence(index),
+        bananas,
+    })
+}
+
+/// The price each buyer sells at when `sequence` first shows up in their changes, or
+/// `None` if it never does.
+pub fn selling_prices(secrets: &[u64], sequence: [i8; 4]) -> Vec<Option<u8>> {
+    let target = sequence
+        .iter()
+        .fold(0, |index, &change| index * 19 + (change + 9) as usize);
+    secrets
+        .iter()
+        .map(|&secret| {
+            changes(secret, ITERATIONS)
+                .tuple_windows()
+                .find(|&(a, b, c, d, e)| change_index(a, b, c, d, e) == target)
+                .map(|(_, _, _, _, e)| e)
+        })
+        .collect()
+}
+
+fn parse_secrets(input: &str) -> Vec<u64> {
+    input
+        .lines()
+        .filter_map(|l| parse::<u64>(l.as_bytes()).ok())
+        .collect()
 }
 
 pub fn part_one(input: &str) -> Option<u64> {
@@ -79,44 +155,30 @@ pub fn part_one(input: &str) -> Option<u64> {
             .sum(),
     )
 }
+
+// `--buyers` prints the winning sequence and what each buyer sells for
 pub fn part_two(input: &str) -> Option<u32> {
-    // let totals: DashMap<u32, u32> = DashMap::new();
-    let totals = input
-        .lines()
-        .par_bridge()
-        .filter_map(|l| parse::<u64>(l.as_bytes()).ok())
-        .map(|n| changes(n, ITERATIONS))
-        .fold(HashMap::<u32, u32>::new, |mut totals, iter| {
-            let mut seen: HashSet<u32, BuildNoHashHasher<u32>> =
-                HashSet::with_capacity_and_hasher(2001, BuildNoHashHasher::default());
-
-            iter.tuple_windows()
-                .map(|(a, b, c, d, e)| (build_key(a, b, c, d, e), e))
-                .filter(|(key, _)| seen.insert(*key))
-                .for_each(|(key, e)| {
-                    *totals.entry(key).or_insert(0) += e as u32;
-                });
+    let secrets = parse_secrets(input);
+    let best = best_sequence(&secrets)?;
 
-            totals
-        })
-        .reduce(HashMap::<u32, u32>::new, |mut a, mut b| {
-            // saves some time on first merge when a is an empty new hashmap
-            if b.len() > a.len() {
-                std::mem::swap(&mut a, &mut b);
+    if flag_once("--buyers") {
+        println!("Best sequence: {:?}", best.changes);
+        for (secret, price) in secrets.iter().zip(selling_prices(&secrets, best.changes)) {
+            match price {
+                Some(price) => println!("{secret}: {price}"),
+                None => println!("{secret}: -"),
             }
+        }
+    }
 
-            b.iter().for_each(|(k, v)| {
-                *a.entry(*k).or_insert(0) += v;
-            });
-            a
-        });
-
-    totals.values().max().copied()
+    Some(best.bananas)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hashbrown::{HashMap, HashSet};
+    use nohash::BuildNoHashHasher;
 
     #[test]
     fn test_process_1() {
@@ -156,4 +218,98 @@ mod tests {
         ));
         assert_eq!(result, Some(23));
     }
+
+    #[test]
+    fn test_best_sequence() {
+        let secrets = [1, 2, 3, 2024];
+        assert_eq!(
+            best_sequence(&secrets),
+            Some(BestSequence {
+                changes: [-2, 1, -1, 3],
+                bananas: 23
+            })
+        );
+        assert_eq!(
+            selling_prices(&secrets, [-2, 1, -1, 3]),
+            vec![Some(7), Some(7), None, Some(9)]
+        );
+        assert_eq!(change_sequence(change_index(9, 0, 9, 0, 0)), [-9, 9, -9, 0]);
+    }
+
+    // The earlier HashMap/HashSet version, kept to check and time the flat arrays against.
+    /// Builds a key for the HashMap given the different prices. Calculate the differences,
+    /// and use them to build the key. Squash them all into a u32
+    fn build_key(a: u8, b: u8, c: u8, d: u8, e: u8) -> u32 {
+        let d1 = ((b as i8 - a as i8) + 10) as u8;
+        let d2 = ((c as i8 - b as i8) + 10) as u8;
+        let d3 = ((d as i8 - c as i8) + 10) as u8;
+        let d4 = ((e as i8 - d as i8) + 10) as u8;
+
+        u32::from_ne_bytes([d1, d2, d3, d4])
+    }
+
+    fn part_two_hashmap(input: &str) -> Option<u32> {
+        let totals = input
+            .lines()
+            .par_bridge()
+            .filter_map(|l| parse::<u64>(l.as_bytes()).ok())
+            .map(|n| changes(n, ITERATIONS))
+            .fold(HashMap::<u32, u32>::new, |mut totals, iter| {
+                let mut seen: HashSet<u32, BuildNoHashHasher<u32>> =
+                    HashSet::with_capacity_and_hasher(2001, BuildNoHashHasher::default());
+
+                iter.tuple_windows()
+                    .map(|(a, b, c, d, e)| (build_key(a, b, c, d, e), e))
+                    .filter(|(key, _)| seen.insert(*key))
+                    .for_each(|(key, e)| {
+                        *totals.entry(key).or_insert(0) += e as u32;
+                    });
+
+                totals
+            })
+            .reduce(HashMap::<u32, u32>::new, |mut a, mut b| {
+                // saves some time on first merge when a is an empty new hashmap
+                if b.len() > a.len() {
+                    std::mem::swap(&mut a, &mut b);
+                }
+
+                b.iter().for_each(|(k, v)| {
+                    *a.entry(*k).or_insert(0) += v;
+                });
+                a
+            });
+
+        totals.values().max().copied()
+    }
+
+    // cargo test --release --bin 22 -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_flat_against_hashmap() {
+        use std::time::Instant;
+
+        // a buyer list the size of a real input
+        let mut state: u64 = 0x2024_0022;
+        let input: String = (0..2000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                format!("{}\n", (state >> 40) % 16777216)
+            })
+            .collect();
+
+        // warm up rayon's thread pool so neither side pays for it
+        part_two(&input);
+
+        let timer = Instant::now();
+        let hashmap = part_two_hashmap(&input);
+        let hashmap_time = timer.elapsed();
+        let timer = Instant::now();
+        let flat = part_two(&input);
+        let flat_time = timer.elapsed();
+
+        println!("HashMap: {hashmap_time:?}, flat array: {flat_time:?}");
+        assert_eq!(flat, hashmap);
+    }
 }
@@ -0,0 +1,738 @@
+use std::error::Error;
+use std::fmt::{Display, Write};
+
+use hashbrown::HashMap;
+use nom::{
+    IResult,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{alphanumeric1, char, space1},
+    combinator::value,
+    sequence::{separated_pair, terminated},
+};
+
+use crate::parse::{ParseError, blank_line, finish, lines, location};
+use crate::template::SolutionError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    And,
+    Or,
+    Xor,
+}
+
+impl GateKind {
+    pub fn apply(self, left: bool, right: bool) -> bool {
+        match self {
+            Self::And => left && right,
+            Self::Or => left || right,
+            Self::Xor => left != right,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::Xor => "XOR",
+        }
+    }
+}
+
+/// A two-input gate between wire ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gate {
+    pub kind: GateKind,
+    pub inputs: (usize, usize),
+    pub output: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitError {
+    /// The named wires feed each other in a loop, usually after a bad swap.
+    Cycle(Vec<String>),
+    /// A wire with neither a gate nor a value.
+    Undriven(String),
+}
+
+impl Display for CircuitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cycle(wires) => write!(f, "wires form a cycle: {}", wires.join(" -> ")),
+            Self::Undriven(wire) => write!(f, "wire {wire} has no value and no gate"),
+        }
+    }
+}
+
+impl Error for CircuitError {}
+
+impl From<CircuitError> for SolutionError {
+    fn from(e: CircuitError) -> Self {
+        SolutionError::new(e.to_string())
+    }
+}
+
+/// A netlist of two-input gates over named wires.
+///
+/// Wires are interned like [`crate::graph::Graph`] nodes, so evaluation works on
+/// dense `Vec<Option<bool>>` values indexed by wire id.
+#[derive(Debug, Clone, Default)]
+pub struct Circuit {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    gates: Vec<Gate>,
+    driver: Vec<Option<usize>>,
+    initial: Vec<Option<bool>>,
+}
+
+fn parse_initial(input: &str) -> IResult<&str, (&str, bool)> {
+    separated_pair(
+        alphanumeric1,
+        tag(": "),
+        alt((value(true, char('1')), value(false, char('0')))),
+    )(input)
+}
+
+fn parse_gate(input: &str) -> IResult<&str, (&str, GateKind, &str, &str)> {
+    let kind = alt((
+        value(GateKind::And, tag("AND")),
+        value(GateKind::Or, tag("OR")),
+        value(GateKind::Xor, tag("XOR")),
+    ));
+    let (input, left) = terminated(alphanumeric1, space1)(input)?;
+    let (input, kind) = terminated(kind, space1)(input)?;
+    let (input, right) = terminated(alphanumeric1, tag(" -> "))(input)?;
+    let (input, output) = alphanumeric1(input)?;
+    Ok((input, (left, kind, right, output)))
+}
+
+impl Circuit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the `x00: 1` initial values, a blank line, then one `a AND b -> c` gate per line.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let (initials, gates) = finish(
+            input,
+            separated_pair(lines(parse_initial), blank_line, lines(parse_gate)),
+        )?;
+
+        let mut circuit = Self::new();
+        for (name, value) in initials {
+            circuit.set_initial(name, value);
+        }
+        for (left, kind, right, output) in gates {
+            if circuit
+                .id(output)
+                .is_some_and(|id| circuit.driver[id].is_some())
+            {
+                // `output` borrows from `input`, so its address gives the position
+                let offset = output.as_ptr() as usize - input.as_ptr() as usize;
+                let (line, column) = location(input, offset);
+                return Err(ParseError {
+                    line,
+                    column,
+                    message: format!("wire {output} is driven by more than one gate"),
+                });
+            }
+            circuit.add_gate(kind, left, right, output);
+        }
+        Ok(circuit)
+    }
+
+    /// Returns the id of `name`, adding it as a new wire if it hasn't been seen.
+    pub fn add_wire(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.ids.insert(name.to_string(), id);
+        self.names.push(name.to_string());
+        self.driver.push(None);
+        self.initial.push(None);
+        id
+    }
+
+    pub fn set_initial(&mut self, name: &str, value: bool) {
+        let id = self.add_wire(name);
+        self.initial[id] = Some(value);
+    }
+
+    /// Adds a gate driving `output`, replacing any gate that drove it before.
+    pub fn add_gate(&mut self, kind: GateKind, left: &str, right: &str, output: &str) {
+        let inputs = (self.add_wire(left), self.add_wire(right));
+        let output = self.add_wire(output);
+        let gate = Gate {
+            kind,
+            inputs,
+            output,
+        };
+        match self.driver[output] {
+            Some(g) => self.gates[g] = gate,
+            None => {
+                self.driver[output] = Some(self.gates.len());
+                self.gates.push(gate);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    /// Maps a list of wire ids to their names.
+    pub fn names_of(&self, ids: &[usize]) -> Vec<&str> {
+        ids.iter().map(|&id| self.name(id)).collect()
+    }
+
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    /// The gate whose output is `wire`, if any.
+    pub fn driver(&self, wire: usize) -> Option<&Gate> {
+        self.driver[wire].map(|g| &self.gates[g])
+    }
+
+    /// Every gate that reads `wire`.
+    pub fn consumers(&self, wire: usize) -> impl Iterator<Item = &Gate> {
+        self.gates
+            .iter()
+            .filter(move |g| g.inputs.0 == wire || g.inputs.1 == wire)
+    }
+
+    /// The wires named `prefix` followed by a number, like `z00`, ordered least significant first.
+    pub fn bus(&self, prefix: char) -> Vec<usize> {
+        let mut bus: Vec<(u32, usize)> = self
+            .names
+            .iter()
+            .enumerate()
+            .filter_map(|(id, name)| {
+                let digits = name.strip_prefix(prefix)?;
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                Some((digits.parse().ok()?, id))
+            })
+            .collect();
+        bus.sort_unstable();
+        bus.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// The values given in the input, indexed by wire id.
+    pub fn initial_values(&self) -> Vec<Option<bool>> {
+        self.initial.clone()
+    }
+
+    /// Sets the bits of `prefix`'s bus from `value`, least significant bit first.
+    pub fn set_bus(&self, values: &mut [Option<bool>], prefix: char, value: u64) {
+        for (bit, wire) in self.bus(prefix).into_iter().enumerate() {
+            values[wire] = Some(bit < 64 && value >> bit & 1 == 1);
+        }
+    }
+
+    /// Reads `prefix`'s bus as a number, if every bit has a value and it fits in 64 bits.
+    pub fn read_bus(&self, values: &[Option<bool>], prefix: char) -> Option<u64> {
+        let bus = self.bus(prefix);
+        if bus.len() > 64 {
+            return None;
+        }
+        bus.iter()
+            .rev()
+            .try_fold(0, |acc, &wire| Some(acc << 1 | values[wire]? as u64))
+    }
+
+    /// Orders the gates so every gate comes after the gates driving its inputs.
+    pub fn topological_order(&self) -> Result<Vec<usize>, CircuitError> {
+        let mut pending = vec![0u8; self.gates.len()];
+        let mut readers: Vec<Vec<usize>> = vec![vec![]; self.len()];
+        for (g, gate) in self.gates.iter().enumerate() {
+            for input in [gate.inputs.0, gate.inputs.1] {
+                readers[input].push(g);
+                if self.driver[input].is_some() {
+                    pending[g] += 1;
+                }
+            }
+        }
+
+        let mut order: Vec<usize> = (0..self.gates.len()).filter(|&g| pending[g] == 0).collect();
+        let mut next = 0;
+        while let Some(&g) = order.get(next) {
+            next += 1;
+            for &reader in &readers[self.gates[g].output] {
+                pending[reader] -= 1;
+                if pending[reader] == 0 {
+                    order.push(reader);
+                }
+            }
+        }
+
+        if order.len() < self.gates.len() {
+            return Err(CircuitError::Cycle(self.find_cycle(&pending)));
+        }
+        Ok(order)
+    }
+
+    // walks back through unresolved gates until one repeats
+    fn find_cycle(&self, pending: &[u8]) -> Vec<String> {
+        let start = (0..self.gates.len()).find(|&g| pending[g] > 0).unwrap();
+        let mut path = vec![start];
+        loop {
+            let gate = &self.gates[*path.last().unwrap()];
+            let previous = [gate.inputs.0, gate.inputs.1]
+                .into_iter()
+                .filter_map(|w| self.driver[w])
+                .find(|&g| pending[g] > 0)
+                .unwrap();
+            if let Some(i) = path.iter().position(|&g| g == previous) {
+                let mut cycle: Vec<String> = path[i..]
+                    .iter()
+                    .rev()
+                    .map(|&g| self.names[self.gates[g].output].clone())
+                    .collect();
+                cycle.push(cycle[0].clone());
+                return cycle;
+            }
+            path.push(previous);
+        }
+    }
+
+    /// Fills in every gate output, starting from the values already set.
+    pub fn evaluate(&self, values: &mut [Option<bool>]) -> Result<(), CircuitError> {
+        let order = self.topological_order()?;
+        self.evaluate_in(&order, values)
+    }
+
+    fn evaluate_in(
+        &self,
+        order: &[usize],
+        values: &mut [Option<bool>],
+    ) -> Result<(), CircuitError> {
+        for &g in order {
+            let gate = self.gates[g];
+            let read = |wire: usize| {
+                values[wire].ok_or_else(|| CircuitError::Undriven(self.names[wire].clone()))
+            };
+            let (left, right) = (read(gate.inputs.0)?, read(gate.inputs.1)?);
+            values[gate.output] = Some(gate.kind.apply(left, right));
+        }
+        Ok(())
+    }
+
+    /// Swaps the outputs of the gates driving wires `a` and `b`.
+    pub fn swap_outputs(&mut self, a: usize, b: usize) {
+        let (ga, gb) = (self.driver[a], self.driver[b]);
+        if let Some(g) = ga {
+            self.gates[g].output = b;
+        }
+        if let Some(g) = gb {
+            self.gates[g].output = a;
+        }
+        self.driver.swap(a, b);
+    }
+
+    /// Renders the circuit as a Graphviz digraph, with one node per wire labelled by its gate.
+    /// Wires in `highlight` are filled in.
+    pub fn to_dot(&self, highlight: &[usize]) -> String {
+        let mut dot = String::from("digraph {\n  rankdir=LR;\n");
+        for (id, name) in self.names.iter().enumerate() {
+            let mut attributes = match self.driver(id) {
+                Some(gate) => format!("label=\"{name}\\n{}\", shape=box", gate.kind.name()),
+                None => "shape=ellipse".to_string(),
+            };
+            if highlight.contains(&id) {
+                attributes.push_str(", style=filled, fillcolor=tomato");
+            }
+            let _ = writeln!(dot, "  \"{name}\" [{attributes}];");
+        }
+        for gate in &self.gates {
+            let output = &self.names[gate.output];
+            for input in [gate.inputs.0, gate.inputs.1] {
+                let _ = writeln!(dot, "  \"{}\" -> \"{output}\";", self.names[input]);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Why [`Circuit::verify_adder`] rejected a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdderMismatch {
+    /// The x, y and z buses don't have the widths of an adder.
+    Shape {
+        x: usize,
+        y: usize,
+        z: usize,
+    },
+    Evaluation(CircuitError),
+    Wrong {
+        x: u64,
+        y: u64,
+        expected: u64,
+        actual: Option<u64>,
+    },
+}
+
+impl Display for AdderMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Shape { x, y, z } => write!(
+                f,
+                "buses of {x}, {y} and {z} bits can't form an adder of up to 63 bits"
+            ),
+            Self::Evaluation(e) => write!(f, "{e}"),
+            Self::Wrong {
+                x,
+                y,
+                expected,
+                actual: Some(actual),
+            } => write!(f, "{x} + {y} gave {actual}, expected {expected}"),
+            Self::Wrong { x, y, .. } => write!(f, "{x} + {y} left some z bits without a value"),
+        }
+    }
+}
+
+/// An adder wiring rule that a gate's output breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdderRule {
+    /// Every output bit but the last is the XOR of the half sum and the carry.
+    OutputNotXor,
+    /// The last output bit is the OR making the final carry.
+    FinalCarryNotOr,
+    /// An XOR of two internal wires is a sum bit, so it must drive a `z` wire.
+    InternalXorNotOutput,
+    /// `x XOR y` must feed the XOR that makes the output bit.
+    HalfSumUnused,
+    /// Both ANDs in a bit must feed the OR that makes the next carry.
+    AndNotIntoOr,
+    /// A carry must feed the next bit's XOR and AND.
+    CarryNotPropagated,
+}
+
+impl Display for AdderRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::OutputNotXor => "output bits must come from an XOR",
+            Self::FinalCarryNotOr => "the top output bit must come from the final carry OR",
+            Self::InternalXorNotOutput => "an XOR of internal wires must drive an output bit",
+            Self::HalfSumUnused => "x XOR y must feed another XOR",
+            Self::AndNotIntoOr => "an AND must feed an OR",
+            Self::CarryNotPropagated => "a carry must feed both an XOR and an AND",
+        })
+    }
+}
+
+/// A wire whose driving gate breaks an [`AdderRule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    pub wire: usize,
+    pub rule: AdderRule,
+}
+
+// up to this many suspects, every way of pairing them up is tried
+const MAX_SUSPECTS: usize = 12;
+
+impl Circuit {
+    fn bit_of(&self, wire: usize, prefix: char) -> Option<u32> {
+        let digits = self.names[wire].strip_prefix(prefix)?;
+        digits.parse().ok()
+    }
+
+    fn is_input(&self, wire: usize) -> bool {
+        self.bit_of(wire, 'x').is_some() || self.bit_of(wire, 'y').is_some()
+    }
+
+    /// Checks every gate against the structure of a ripple-carry adder adding the `x` and `y`
+    /// buses into `z`. A wire can break more than one rule.
+    pub fn adder_faults(&self) -> Vec<Fault> {
+        let width = self.bus('x').len() as u32;
+        let mut faults = Vec::new();
+
+        for gate in &self.gates {
+            let output = gate.output;
+            let first_bit = [gate.inputs.0, gate.inputs.1]
+                .iter()
+                .any(|&w| self.bit_of(w, 'x') == Some(0));
+            let from_inputs = self.is_input(gate.inputs.0) && self.is_input(gate.inputs.1);
+            let feeds = |kind: GateKind| self.consumers(output).any(|g| g.kind == kind);
+            let mut fault = |rule| faults.push(Fault { wire: output, rule });
+
+            let output_bit = self.bit_of(output, 'z');
+            match output_bit {
+                Some(bit) if bit == width && width > 1 && gate.kind != GateKind::Or => {
+                    fault(AdderRule::FinalCarryNotOr)
+                }
+                Some(bit) if bit < width && gate.kind != GateKind::Xor => {
+                    fault(AdderRule::OutputNotXor)
+                }
+                _ => {}
+            }
+            let is_output = output_bit.is_some();
+
+            match gate.kind {
+                GateKind::Xor if !from_inputs && !is_output => {
+                    fault(AdderRule::InternalXorNotOutput)
+                }
+                GateKind::Xor if from_inputs && !first_bit && !feeds(GateKind::Xor) => {
+                    fault(AdderRule::HalfSumUnused)
+                }
+                GateKind::And if !first_bit && !feeds(GateKind::Or) => {
+                    fault(AdderRule::AndNotIntoOr)
+                }
+                // the first bit's AND is the first carry
+                GateKind::And | GateKind::Or
+                    if (gate.kind == GateKind::Or || first_bit)
+                        && !is_output
+                        && !(feeds(GateKind::Xor) && feeds(GateKind::And)) =>
+                {
+                    fault(AdderRule::CarryNotPropagated)
+                }
+                _ => {}
+            }
+        }
+
+        faults.sort_by(|a, b| self.names[a.wire].cmp(&self.names[b.wire]));
+        faults
+    }
+
+    /// Adds random and edge-case numbers through the circuit and compares against `x + y`.
+    ///
+    /// Any bit width up to 63 works, as long as `z` is one bit wider than `x` and `y`.
+    pub fn verify_adder(&self, trials: usize, seed: u64) -> Result<(), AdderMismatch> {
+        let (x, y, z) = (
+            self.bus('x').len(),
+            self.bus('y').len(),
+            self.bus('z').len(),
+        );
+        if x != y || z != x + 1 || x == 0 || x > 63 {
+            return Err(AdderMismatch::Shape { x, y, z });
+        }
+        let order = self
+            .topological_order()
+            .map_err(AdderMismatch::Evaluation)?;
+        let max = (1u64 << x) - 1;
+
+        // every single bit, and carries rippling the whole width
+        let edges = (0..x).flat_map(|bit| [(1 << bit, 0), (0, 1 << bit), (1 << bit, 1 << bit)]);
+        let fixed = [(0, 0), (max, 1), (1, max), (max, max)];
+        let mut state = seed | 1;
+        let random = std::iter::repeat_with(|| {
+            let a = xorshift(&mut state) & max;
+            let b = xorshift(&mut state) & max;
+            (a, b)
+        });
+
+        for (a, b) in fixed.into_iter().chain(edges).chain(random.take(trials)) {
+            let mut values = self.initial_values();
+            self.set_bus(&mut values, 'x', a);
+            self.set_bus(&mut values, 'y', b);
+            self.evaluate_in(&order, &mut values)
+                .map_err(AdderMismatch::Evaluation)?;
+            let actual = self.read_bus(&values, 'z');
+            if actual != Some(a + b) {
+                return Err(AdderMismatch::Wrong {
+                    x: a,
+                    y: b,
+                    expected: a + b,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the pairs of gate outputs that were swapped, by pairing up the wires from
+    /// [`Circuit::adder_faults`] and keeping the pairing that passes [`Circuit::verify_adder`].
+    pub fn find_adder_swaps(&self, trials: usize) -> Option<Vec<(usize, usize)>> {
+        let mut suspects: Vec<usize> = self.adder_faults().iter().map(|f| f.wire).collect();
+        suspects.dedup();
+        if !suspects.len().is_multiple_of(2) || suspects.len() > MAX_SUSPECTS {
+            return None;
+        }
+
+        let mut pairs = Vec::new();
+        self.try_pairings(&suspects, &mut pairs, trials)
+            .then_some(pairs)
+    }
+
+    fn try_pairings(&self, rest: &[usize], pairs: &mut Vec<(usize, usize)>, trials: usize) -> bool {
+        let Some((&first, rest)) = rest.split_first() else {
+            let mut candidate = self.clone();
+            pairs
+                .iter()
+                .for_each(|&(a, b)| candidate.swap_outputs(a, b));
+            return candidate.verify_adder(trials, 0x2024_1224).is_ok();
+        };
+
+        for i in 0..rest.len() {
+            pairs.push((first, rest[i]));
+            let remaining: Vec<usize> = rest[..i].iter().chain(&rest[i + 1..]).copied().collect();
+            if self.try_pairings(&remaining, pairs, trials) {
+                return true;
+            }
+            pairs.pop();
+        }
+        false
+    }
+}
+
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a textbook ripple-carry adder with readable internal names
+    fn adder(width: usize) -> Circuit {
+        let mut circuit = Circuit::new();
+        for bit in 0..width {
+            circuit.set_initial(&format!("x{bit:02}"), false);
+            circuit.set_initial(&format!("y{bit:02}"), false);
+        }
+        let carry = |bit: usize| {
+            if bit + 1 == width {
+                format!("z{width:02}")
+            } else {
+                format!("c{bit:02}")
+            }
+        };
+
+        circuit.add_gate(GateKind::Xor, "x00", "y00", "z00");
+        circuit.add_gate(GateKind::And, "x00", "y00", &carry(0));
+        for bit in 1..width {
+            let (x, y) = (format!("x{bit:02}"), format!("y{bit:02}"));
+            let (s, a, b) = (
+                format!("s{bit:02}"),
+                format!("a{bit:02}"),
+                format!("b{bit:02}"),
+            );
+            circuit.add_gate(GateKind::Xor, &x, &y, &s);
+            circuit.add_gate(GateKind::And, &x, &y, &a);
+            circuit.add_gate(GateKind::Xor, &s, &carry(bit - 1), &format!("z{bit:02}"));
+            circuit.add_gate(GateKind::And, &s, &carry(bit - 1), &b);
+            circuit.add_gate(GateKind::Or, &a, &b, &carry(bit));
+        }
+        circuit
+    }
+
+    #[test]
+    fn parse_and_evaluate() {
+        let circuit = Circuit::parse(
+            "x00: 1\nx01: 1\ny00: 0\ny01: 1\n\nx00 AND y00 -> z00\nx01 XOR y01 -> z02\nx01 OR y01 -> z01\n",
+        )
+        .unwrap();
+        assert_eq!(
+            circuit.names_of(&circuit.bus('z')),
+            vec!["z00", "z01", "z02"]
+        );
+
+        let mut values = circuit.initial_values();
+        circuit.evaluate(&mut values).unwrap();
+        assert_eq!(circuit.read_bus(&values, 'z'), Some(0b010));
+
+        let mut values = circuit.initial_values();
+        circuit.set_bus(&mut values, 'y', 0b01);
+        circuit.evaluate(&mut values).unwrap();
+        assert_eq!(circuit.read_bus(&values, 'z'), Some(0b111));
+
+        let err = Circuit::parse("x00: 1\n\nx00 AND x00 -> z00\nx00 OR x00 -> z00\n").unwrap_err();
+        assert_eq!((err.line, err.column), (4, 15));
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let mut circuit = adder(4);
+        // the sum XOR would now read its own output
+        circuit.swap_outputs(circuit.id("z02").unwrap(), circuit.id("s02").unwrap());
+        let mut values = circuit.initial_values();
+        let Err(CircuitError::Cycle(wires)) = circuit.evaluate(&mut values) else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(wires.first(), wires.last());
+        assert!(wires.contains(&"s02".to_string()));
+        assert!(matches!(
+            circuit.verify_adder(10, 1),
+            Err(AdderMismatch::Evaluation(_))
+        ));
+
+        let dot = circuit.to_dot(&[circuit.id("s02").unwrap()]);
+        assert!(
+            dot.contains(
+                "\"s02\" [label=\"s02\\nXOR\", shape=box, style=filled, fillcolor=tomato];"
+            )
+        );
+        assert!(dot.contains("\"x00\" -> \"z00\";"));
+    }
+
+    #[test]
+    fn verifies_adders_of_any_width() {
+        for width in [1, 2, 5, 13, 63] {
+            let circuit = adder(width);
+            assert_eq!(circuit.verify_adder(200, width as u64), Ok(()));
+            assert!(circuit.adder_faults().is_empty(), "width {width}");
+        }
+        assert!(matches!(
+            Circuit::parse("x00: 1\ny00: 1\n\nx00 AND y00 -> z00\n")
+                .unwrap()
+                .verify_adder(1, 1),
+            Err(AdderMismatch::Shape { x: 1, y: 1, z: 1 })
+        ));
+    }
+
+    #[test]
+    fn finds_swapped_outputs() {
+        let correct = adder(12);
+        let mut circuit = correct.clone();
+        let id = |name| correct.id(name).unwrap();
+        circuit.swap_outputs(id("z04"), id("a04"));
+        circuit.swap_outputs(id("s06"), id("a06"));
+
+        assert!(matches!(
+            circuit.verify_adder(100, 7),
+            Err(AdderMismatch::Wrong { .. })
+        ));
+
+        let faults = circuit.adder_faults();
+        let broken: Vec<(&str, AdderRule)> = faults
+            .iter()
+            .map(|f| (circuit.name(f.wire), f.rule))
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                ("a04", AdderRule::InternalXorNotOutput),
+                ("a06", AdderRule::HalfSumUnused),
+                ("s06", AdderRule::AndNotIntoOr),
+                ("z04", AdderRule::OutputNotXor),
+                ("z04", AdderRule::AndNotIntoOr),
+            ]
+        );
+
+        let swaps = circuit.find_adder_swaps(100).unwrap();
+        let mut names: Vec<(&str, &str)> = swaps
+            .iter()
+            .map(|&(a, b)| (circuit.name(a), circuit.name(b)))
+            .collect();
+        names.sort();
+        assert_eq!(names, vec![("a04", "z04"), ("a06", "s06")]);
+    }
+}
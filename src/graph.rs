@@ -0,0 +1,329 @@
+use hashbrown::HashMap;
+use std::fmt::Write;
+
+use crate::bitset::BitSet;
+
+/// An undirected graph whose nodes are interned names.
+///
+/// Nodes are identified by their insertion index, so algorithms can work
+/// with `usize` ids and dense [`BitSet`]s instead of hashing strings.
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    edges: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a graph from one `a<separator>b` edge per line, e.g. `kh-tc`.
+    pub fn from_edge_list(input: &str, separator: char) -> Self {
+        let mut graph = Self::new();
+        input
+            .lines()
+            .filter_map(|l| l.split_once(separator))
+            .for_each(|(a, b)| graph.add_edge(a, b));
+        graph
+    }
+
+    /// Returns the id of `name`, adding it as a new node if it hasn't been seen.
+    pub fn add_node(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.ids.insert(name.to_string(), id);
+        self.names.push(name.to_string());
+        self.edges.push(vec![]);
+        id
+    }
+
+    /// Adds an undirected edge, creating either node if needed. Duplicate edges are ignored.
+    pub fn add_edge(&mut self, a: &str, b: &str) {
+        let a_id = self.add_node(a);
+        let b_id = self.add_node(b);
+        if a_id == b_id || self.edges[a_id].contains(&b_id) {
+            return;
+        }
+        self.edges[a_id].push(b_id);
+        self.edges[b_id].push(a_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    /// Iterates `(id, name)` for every node in insertion order.
+    pub fn nodes(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.names.iter().map(String::as_str).enumerate()
+    }
+
+    pub fn neighbors(&self, id: usize) -> &[usize] {
+        &self.edges[id]
+    }
+
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        self.edges[a].contains(&b)
+    }
+
+    /// Maps a list of ids to their names.
+    pub fn names_of(&self, ids: &[usize]) -> Vec<&str> {
+        ids.iter().map(|&id| self.name(id)).collect()
+    }
+
+    /// Adjacency of every node as a bitset. Costs `len()^2 / 8` bytes, so
+    /// this is meant for the few-thousand-node graphs AoC hands out.
+    pub fn adjacency_bits(&self) -> Vec<BitSet> {
+        self.edges
+            .iter()
+            .map(|edges| {
+                let mut set = BitSet::new(self.len());
+                edges.iter().for_each(|&e| set.insert(e));
+                set
+            })
+            .collect()
+    }
+
+    /// Finds a maximum clique using Bron–Kerbosch with pivoting. The ids are sorted.
+    pub fn maximum_clique(&self) -> Vec<usize> {
+        let adjacency = self.adjacency_bits();
+        let mut max_clique = Vec::new();
+        let mut potential_clique = Vec::new();
+
+        bron_kerbosch(
+            &adjacency,
+            &mut potential_clique,
+            BitSet::full(self.len()),
+            BitSet::new(self.len()),
+            &mut max_clique,
+        );
+
+        max_clique.sort_unstable();
+        max_clique
+    }
+
+    /// Enumerates every clique with exactly `k` nodes. Each clique is listed once, with sorted ids.
+    pub fn cliques_of_size(&self, k: usize) -> Vec<Vec<usize>> {
+        let mut found = Vec::new();
+        if k == 0 {
+            return found;
+        }
+
+        // only extend cliques with higher ids so every clique is built in one order
+        let higher: Vec<BitSet> = self
+            .edges
+            .iter()
+            .enumerate()
+            .map(|(id, edges)| {
+                let mut set = BitSet::new(self.len());
+                edges
+                    .iter()
+                    .filter(|&&e| e > id)
+                    .for_each(|&e| set.insert(e));
+                set
+            })
+            .collect();
+
+        let mut clique = Vec::with_capacity(k);
+        for id in 0..self.len() {
+            clique.push(id);
+            extend_clique(&higher, &mut clique, higher[id].clone(), k, &mut found);
+            clique.pop();
+        }
+
+        found
+    }
+
+    /// Every set of three mutually connected nodes.
+    pub fn triangles(&self) -> Vec<Vec<usize>> {
+        self.cliques_of_size(3)
+    }
+
+    /// Groups the nodes into connected components, each sorted by id.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        let mut seen = BitSet::new(self.len());
+        let mut components = Vec::new();
+
+        for start in 0..self.len() {
+            if seen.contains(start) {
+                continue;
+            }
+            seen.insert(start);
+            let mut component = vec![start];
+            let mut stack = vec![start];
+            while let Some(id) = stack.pop() {
+                for &next in &self.edges[id] {
+                    if !seen.contains(next) {
+                        seen.insert(next);
+                        component.push(next);
+                        stack.push(next);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+
+        components
+    }
+
+    /// Renders the graph in Graphviz DOT format. Nodes in `highlight` are filled in.
+    pub fn to_dot(&self, highlight: &[usize]) -> String {
+        let mut dot = String::from("graph {\n");
+        for (id, name) in self.nodes() {
+            if highlight.contains(&id) {
+                let _ = writeln!(dot, "  \"{name}\" [style=filled, fillcolor=gold];");
+            } else {
+                let _ = writeln!(dot, "  \"{name}\";");
+            }
+        }
+        for (a, edges) in self.edges.iter().enumerate() {
+            for &b in edges.iter().filter(|&&b| b > a) {
+                let _ = writeln!(dot, "  \"{}\" -- \"{}\";", self.names[a], self.names[b]);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn bron_kerbosch(
+    adjacency: &[BitSet],
+    potential_clique: &mut Vec<usize>,
+    mut candidates: BitSet,
+    mut excluded: BitSet,
+    max_clique: &mut Vec<usize>,
+) {
+    if candidates.is_empty() {
+        if excluded.is_empty() && potential_clique.len() > max_clique.len() {
+            max_clique.clone_from(potential_clique);
+        }
+        return;
+    }
+
+    // a clique can't grow past what's already in hand plus every candidate
+    if potential_clique.len() + candidates.len() <= max_clique.len() {
+        return;
+    }
+
+    let pivot = choose_pivot(adjacency, &candidates, &excluded);
+    let to_visit = candidates.difference(&adjacency[pivot]);
+
+    for node in to_visit.iter() {
+        potential_clique.push(node);
+        bron_kerbosch(
+            adjacency,
+            potential_clique,
+            candidates.intersection(&adjacency[node]),
+            excluded.intersection(&adjacency[node]),
+            max_clique,
+        );
+        potential_clique.pop();
+
+        candidates.remove(node);
+        excluded.insert(node);
+    }
+}
+
+// pick the node that leaves the fewest candidates to branch on
+fn choose_pivot(adjacency: &[BitSet], candidates: &BitSet, excluded: &BitSet) -> usize {
+    candidates
+        .union(excluded)
+        .iter()
+        .max_by_key(|&node| candidates.intersection_len(&adjacency[node]))
+        .unwrap()
+}
+
+fn extend_clique(
+    higher: &[BitSet],
+    clique: &mut Vec<usize>,
+    candidates: BitSet,
+    k: usize,
+    found: &mut Vec<Vec<usize>>,
+) {
+    if clique.len() == k {
+        found.push(clique.clone());
+        return;
+    }
+    if clique.len() + candidates.len() < k {
+        return;
+    }
+
+    for next in candidates.iter() {
+        clique.push(next);
+        extend_clique(
+            higher,
+            clique,
+            candidates.intersection(&higher[next]),
+            k,
+            found,
+        );
+        clique.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Graph {
+        // a 4-clique (a,b,c,d), a triangle hanging off it (d,e,f) and a separate pair
+        Graph::from_edge_list(
+            "a-b\na-c\na-d\nb-c\nb-d\nc-d\nd-e\ne-f\nf-d\nx-y\nx-y\n",
+            '-',
+        )
+    }
+
+    #[test]
+    fn interning() {
+        let graph = sample();
+        assert_eq!(graph.len(), 8);
+        assert_eq!(graph.id("a"), Some(0));
+        assert_eq!(graph.name(4), "e");
+        assert_eq!(graph.neighbors(graph.id("x").unwrap()).len(), 1);
+        assert!(graph.has_edge(0, 3));
+        assert!(!graph.has_edge(0, 4));
+    }
+
+    #[test]
+    fn cliques() {
+        let graph = sample();
+        assert_eq!(
+            graph.names_of(&graph.maximum_clique()),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(graph.triangles().len(), 5);
+        assert_eq!(graph.cliques_of_size(4), vec![vec![0, 1, 2, 3]]);
+        assert!(graph.cliques_of_size(5).is_empty());
+        assert_eq!(graph.cliques_of_size(2).len(), 10);
+    }
+
+    #[test]
+    fn components_and_dot() {
+        let graph = sample();
+        assert_eq!(
+            graph.connected_components(),
+            vec![vec![0, 1, 2, 3, 4, 5], vec![6, 7]]
+        );
+
+        let dot = graph.to_dot(&[6]);
+        assert!(dot.starts_with("graph {\n"));
+        assert!(dot.contains("\"x\" [style=filled, fillcolor=gold];"));
+        assert_eq!(dot.matches(" -- ").count(), 10);
+    }
+}
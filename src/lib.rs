@@ -1,4 +1,10 @@
+pub mod bitset;
+pub mod circuit;
+pub mod graph;
+pub mod math;
+pub mod parse;
 pub mod template;
+pub mod visualize;
 
 // Use this file to add helper functions and additional modules.
 
@@ -7,6 +7,7 @@ use advent_of_code::template::Day;
 use std::process;
 
 mod args {
+    use advent_of_code::template::commands::solve;
     use advent_of_code::template::Day;
     use std::process;
 
@@ -27,6 +28,7 @@ mod args {
             release: bool,
             dhat: bool,
             submit: Option<u8>,
+            solution_args: Vec<String>,
         },
         All {
             release: bool,
@@ -73,6 +75,7 @@ mod args {
                 release: args.contains("--release"),
                 submit: args.opt_value_from_str("--submit")?,
                 dhat: args.contains("--dhat"),
+                solution_args: solution_args(&mut args)?,
             },
             #[cfg(feature = "today")]
             Some("today") => AppArguments::Today,
@@ -93,6 +96,25 @@ mod args {
 
         Ok(app_args)
     }
+
+    /// Collects the flags that are passed through to the solution binary.
+    fn solution_args(
+        args: &mut pico_args::Arguments,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut forwarded = Vec::new();
+        for flag in solve::SOLUTION_FLAGS {
+            if args.contains(flag) {
+                forwarded.push(flag.to_string());
+            }
+        }
+        for option in solve::SOLUTION_OPTIONS {
+            if let Some(value) = args.opt_value_from_str::<_, String>(option)? {
+                forwarded.push(option.to_string());
+                forwarded.push(value);
+            }
+        }
+        Ok(forwarded)
+    }
 }
 
 fn main() {
@@ -106,7 +128,11 @@ fn main() {
             AppArguments::Time { day, all, store } => time::handle(day, all, store),
             AppArguments::Download { day } => download::handle(day),
             AppArguments::Read { day } => read::handle(day),
-            AppArguments::Scaffold { day, download, overwrite } => {
+            AppArguments::Scaffold {
+                day,
+                download,
+                overwrite,
+            } => {
                 scaffold::handle(day, overwrite);
                 if download {
                     download::handle(day);
@@ -117,7 +143,8 @@ fn main() {
                 release,
                 dhat,
                 submit,
-            } => solve::handle(day, release, dhat, submit),
+                solution_args,
+            } => solve::handle(day, release, dhat, submit, &solution_args),
             #[cfg(feature = "today")]
             AppArguments::Today => {
                 match Day::today() {
@@ -0,0 +1,349 @@
+use num::rational::Ratio;
+use num::{Integer, Signed, Zero};
+
+/// Greatest common divisor. Always non-negative, and `gcd(0, 0) == 0`.
+pub fn gcd<T: Integer>(a: T, b: T) -> T {
+    a.gcd(&b)
+}
+
+/// Least common multiple. Always non-negative, and zero if either input is zero.
+pub fn lcm<T: Integer>(a: T, b: T) -> T {
+    a.lcm(&b)
+}
+
+/// Least common multiple of every value, or one for an empty iterator.
+pub fn lcm_all<T: Integer>(values: impl IntoIterator<Item = T>) -> T {
+    values.into_iter().fold(T::one(), lcm)
+}
+
+/// Extended Euclid: returns `(g, x, y)` with `a * x + b * y == g` and `g >= 0`.
+pub fn extended_gcd<T: Integer + Signed + Copy>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::one(), T::zero());
+    let (mut old_y, mut y) = (T::zero(), T::one());
+
+    while !r.is_zero() {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// The inverse of `a` modulo `m`, in `0..m`, if `m` is positive and `a` and `m` are coprime.
+pub fn mod_inverse<T: Integer + Signed + Copy>(a: T, m: T) -> Option<T> {
+    if !m.is_positive() {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(a.mod_floor(&m), m);
+    if g.is_one() {
+        Some(x.mod_floor(&m))
+    } else {
+        None
+    }
+}
+
+/// `base.pow(exp) % modulus` without overflowing.
+pub fn modpow(base: u64, mut exp: u64, modulus: u64) -> u64 {
+    if modulus == 1 {
+        return 0;
+    }
+    let modulus = modulus as u128;
+    let mut base = base as u128 % modulus;
+    let mut result = 1;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+
+    result as u64
+}
+
+/// Chinese remainder theorem over `(residue, modulus)` pairs.
+///
+/// Moduli don't need to be coprime, but must be positive. Returns `(x, lcm)` with
+/// `0 <= x < lcm`, or `None` if a modulus isn't positive, the congruences contradict each
+/// other or `lcm` overflows.
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    let (mut x, mut m) = (0i128, 1i128);
+
+    for &(residue, modulus) in congruences {
+        if modulus <= 0 {
+            return None;
+        }
+        let modulus = modulus as i128;
+        let residue = (residue as i128).mod_floor(&modulus);
+
+        let (g, p, _) = extended_gcd(m, modulus);
+        let diff = residue - x;
+        if diff % g != 0 {
+            return None;
+        }
+
+        let step = modulus / g;
+        let k = (diff / g).mod_floor(&step) * p.mod_floor(&step) % step;
+        x += m * k;
+        m *= step;
+        x = x.mod_floor(&m);
+
+        if m > i64::MAX as i128 {
+            return None;
+        }
+    }
+
+    Some((x as i64, m as i64))
+}
+
+/// Outcome of [`solve_linear`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinearSolution {
+    Unique(Vec<Ratio<i128>>),
+    Infinite,
+    Inconsistent,
+}
+
+impl LinearSolution {
+    /// The unique solution, if there is one and every value is an integer.
+    pub fn integers(&self) -> Option<Vec<i64>> {
+        match self {
+            Self::Unique(values) => values
+                .iter()
+                .map(|v| {
+                    if v.is_integer() {
+                        i64::try_from(v.to_integer()).ok()
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Solves `matrix * x = rhs` exactly with Gaussian elimination over rationals.
+///
+/// `matrix` is a list of rows and may be non-square; the solution is unique
+/// only when the rank equals the number of columns.
+pub fn solve_linear(matrix: &[Vec<i64>], rhs: &[i64]) -> LinearSolution {
+    let columns = matrix.first().map_or(0, Vec::len);
+    let mut rows: Vec<Vec<Ratio<i128>>> = matrix
+        .iter()
+        .zip(rhs)
+        .map(|(row, &r)| {
+            row.iter()
+                .chain([&r])
+                .map(|&v| Ratio::from_integer(v as i128))
+                .collect()
+        })
+        .collect();
+
+    let mut rank = 0;
+    for col in 0..columns {
+        let Some(pivot) = (rank..rows.len()).find(|&r| !rows[r][col].is_zero()) else {
+            continue;
+        };
+        rows.swap(rank, pivot);
+
+        let lead = rows[rank][col];
+        rows[rank].iter_mut().for_each(|v| *v /= lead);
+
+        for r in 0..rows.len() {
+            if r == rank || rows[r][col].is_zero() {
+                continue;
+            }
+            let factor = rows[r][col];
+            let pivot_row = rows[rank].clone();
+            rows[r]
+                .iter_mut()
+                .zip(&pivot_row)
+                .skip(col)
+                .for_each(|(v, &p)| *v -= p * factor);
+        }
+        rank += 1;
+    }
+
+    // a leftover row of zeros equal to something non-zero
+    if rows[rank..].iter().any(|row| !row[columns].is_zero()) {
+        return LinearSolution::Inconsistent;
+    }
+    if rank < columns {
+        return LinearSolution::Infinite;
+    }
+
+    LinearSolution::Unique(rows[..columns].iter().map(|row| row[columns]).collect())
+}
+
+/// Finds non-negative integers `(n, m)` with `n * a + m * b == target` that
+/// minimise `n * costs.0 + m * costs.1`. Costs must be non-negative.
+///
+/// Handles the degenerate case of `a` and `b` being collinear, where the
+/// system has either no solution or a whole line of them.
+pub fn min_cost_combination(
+    a: (i64, i64),
+    b: (i64, i64),
+    target: (i64, i64),
+    costs: (i64, i64),
+) -> Option<(i64, i64)> {
+    let system = solve_linear(&[vec![a.0, b.0], vec![a.1, b.1]], &[target.0, target.1]);
+    match system {
+        LinearSolution::Inconsistent => None,
+        LinearSolution::Unique(_) => {
+            let solution = system.integers()?;
+            if solution.iter().all(|&v| v >= 0) {
+                Some((solution[0], solution[1]))
+            } else {
+                None
+            }
+        }
+        LinearSolution::Infinite => {
+            // both rows say the same thing, so solve whichever one isn't all zeros
+            if (a.0, b.0) != (0, 0) {
+                min_cost_single(a.0, b.0, target.0, costs)
+            } else {
+                min_cost_single(a.1, b.1, target.1, costs)
+            }
+        }
+    }
+}
+
+// non-negative integer n, m with n * u + m * v == t, minimising cost
+fn min_cost_single(u: i64, v: i64, t: i64, costs: (i64, i64)) -> Option<(i64, i64)> {
+    let (u, v, t) = (u as i128, v as i128, t as i128);
+    let (cu, cv) = (costs.0 as i128, costs.1 as i128);
+
+    let to_i64 = |n: i128, m: i128| Some((i64::try_from(n).ok()?, i64::try_from(m).ok()?));
+
+    match (u, v) {
+        (0, 0) => (t == 0).then_some((0, 0)),
+        (0, v) => (t % v == 0 && t / v >= 0).then(|| to_i64(0, t / v))?,
+        (u, 0) => (t % u == 0 && t / u >= 0).then(|| to_i64(t / u, 0))?,
+        (u, v) => {
+            let (g, x, y) = extended_gcd(u, v);
+            if t % g != 0 {
+                return None;
+            }
+            // every solution is n = n0 + k * dn, m = m0 - k * dm
+            let (n0, m0) = (x * (t / g), y * (t / g));
+            let (dn, dm) = (v / g, u / g);
+
+            let (mut low, mut high) = (None, None);
+            for (start, slope) in [(n0, dn), (m0, -dm)] {
+                // start + k * slope >= 0
+                if slope > 0 {
+                    let bound = Integer::div_ceil(&-start, &slope);
+                    low = Some(low.map_or(bound, |l: i128| l.max(bound)));
+                } else {
+                    let bound = Integer::div_floor(&start, &-slope);
+                    high = Some(high.map_or(bound, |h: i128| h.min(bound)));
+                }
+            }
+
+            let cost_slope = cu * dn - cv * dm;
+            let k = match (low, high) {
+                (Some(l), Some(h)) if l > h => return None,
+                (Some(l), _) if cost_slope >= 0 => l,
+                (_, Some(h)) => h,
+                (Some(l), None) => l,
+                (None, None) => unreachable!("at least one bound exists for non-zero u and v"),
+            };
+
+            to_i64(n0 + k * dn, m0 - k * dm)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_family() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-4isize, 6), 2);
+        assert_eq!(lcm(4u64, 6), 12);
+        assert_eq!(lcm_all([2, 3, 4, 5]), 60);
+
+        let (g, x, y) = extended_gcd(240i64, 46);
+        assert_eq!(g, 2);
+        assert_eq!(240 * x + 46 * y, 2);
+
+        assert_eq!(mod_inverse(3i64, 11), Some(4));
+        assert_eq!(mod_inverse(-3i64, 11), Some(7));
+        assert_eq!(mod_inverse(2i64, 4), None);
+        assert_eq!(mod_inverse(3i64, 0), None);
+
+        assert_eq!(modpow(4, 13, 497), 445);
+        assert_eq!(modpow(u64::MAX, 2, u64::MAX - 1), 1);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(3, 4), (1, 6)]), Some((7, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[(-1, 101), (-2, 103)]), Some((5251, 10403)));
+        assert_eq!(crt(&[(2, 3), (0, 0)]), None);
+        assert_eq!(crt(&[(1, -4)]), None);
+    }
+
+    #[test]
+    fn linear_systems() {
+        let unique = solve_linear(&[vec![2, 1], vec![1, 3]], &[3, 5]);
+        assert_eq!(
+            unique,
+            LinearSolution::Unique(vec![Ratio::new(4, 5), Ratio::new(7, 5)])
+        );
+        assert_eq!(unique.integers(), None);
+
+        let integral = solve_linear(
+            &[vec![1, 1, 1], vec![0, 2, 5], vec![2, 5, -1]],
+            &[6, -4, 27],
+        );
+        assert_eq!(integral.integers(), Some(vec![5, 3, -2]));
+
+        assert_eq!(
+            solve_linear(&[vec![1, 2], vec![2, 4]], &[3, 6]),
+            LinearSolution::Infinite
+        );
+        assert_eq!(
+            solve_linear(&[vec![1, 2], vec![2, 4]], &[3, 7]),
+            LinearSolution::Inconsistent
+        );
+    }
+
+    #[test]
+    fn min_cost() {
+        // the first claw machine from day 13
+        assert_eq!(
+            min_cost_combination((94, 34), (22, 67), (8400, 5400), (3, 1)),
+            Some((80, 40))
+        );
+        // collinear buttons: B is cheaper per unit distance, so use as many B presses as fit
+        assert_eq!(
+            min_cost_combination((2, 2), (3, 3), (12, 12), (3, 1)),
+            Some((0, 4))
+        );
+        // collinear, but A is cheaper per unit distance
+        assert_eq!(
+            min_cost_combination((3, 6), (1, 2), (10, 20), (1, 3)),
+            Some((3, 1))
+        );
+        // collinear but the prize is off the line
+        assert_eq!(min_cost_combination((2, 2), (3, 3), (12, 13), (3, 1)), None);
+        // collinear, on the line, but no non-negative combination
+        assert_eq!(min_cost_combination((2, 2), (4, 4), (7, 7), (3, 1)), None);
+        // unique but negative
+        assert_eq!(min_cost_combination((1, 0), (0, 1), (-1, 2), (3, 1)), None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use nom::{
+    IResult, Parser,
+    bytes::complete::tag,
+    character::complete::{char, digit1, line_ending, one_of},
+    combinator::{map_res, opt, recognize},
+    error::ErrorKind,
+    multi::separated_list1,
+    sequence::{pair, preceded, separated_pair},
+};
+
+/// A parse failure pinned to a 1-based line and column of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Builds an error for the position where `remaining` starts inside `input`.
+    pub fn at(input: &str, remaining: &str, message: impl Into<String>) -> Self {
+        let (line, column) = location(input, input.len() - remaining.len());
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// 1-based `(line, column)` of a byte offset into `input`.
+pub fn location(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Runs `parser` over the whole input, allowing only trailing whitespace to be left over.
+pub fn finish<'a, T>(
+    input: &'a str,
+    mut parser: impl Parser<&'a str, T, nom::error::Error<&'a str>>,
+) -> Result<T, ParseError> {
+    match parser.parse(input) {
+        Ok((remaining, value)) => {
+            if remaining.trim().is_empty() {
+                Ok(value)
+            } else {
+                Err(ParseError::at(
+                    input,
+                    remaining.trim_start(),
+                    "unexpected trailing input",
+                ))
+            }
+        }
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(ParseError::at(
+            input,
+            e.input,
+            format!("unexpected input ({})", e.code.description()),
+        )),
+        Err(nom::Err::Incomplete(_)) => Err(ParseError::at(input, "", "incomplete input")),
+    }
+}
+
+/// An unsigned integer, e.g. `42`.
+pub fn unsigned<T: FromStr>(input: &str) -> IResult<&str, T> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// An integer with an optional sign, e.g. `-3` or `+7`.
+pub fn signed<T: FromStr>(input: &str) -> IResult<&str, T> {
+    map_res(recognize(pair(opt(one_of("+-")), digit1)), str::parse)(input)
+}
+
+/// Two integers split by `separator`, e.g. `3,-4`.
+pub fn number_pair<'a, T: FromStr>(
+    separator: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (T, T)> {
+    move |input| separated_pair(signed, tag(separator), signed)(input)
+}
+
+/// A `key=x,y` pair such as day 14's `p=0,4`.
+pub fn key_pair<'a, T: FromStr>(key: &'a str) -> impl FnMut(&'a str) -> IResult<&'a str, (T, T)> {
+    move |input| preceded(pair(tag(key), char('=')), number_pair(","))(input)
+}
+
+/// One or more `item`s split by `separator`, e.g. `75,47,61`.
+pub fn list<'a, T>(
+    separator: &'a str,
+    item: impl Parser<&'a str, T, nom::error::Error<&'a str>>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<T>> {
+    separated_list1(tag(separator), item)
+}
+
+/// One `item` per line, up to a blank line or the end of the input.
+///
+/// Every non-blank line must be an `item`, so a bad line fails with the item's own error
+/// rather than stopping the list early. The final line ending is left for [`finish`] or
+/// [`blank_line`].
+pub fn lines<'a, T>(
+    mut item: impl Parser<&'a str, T, nom::error::Error<&'a str>>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<T>> {
+    move |input| {
+        let (mut rest, first) = item.parse(input)?;
+        let mut items = vec![first];
+
+        while let Ok((after, _)) = line_ending::<&str, nom::error::Error<&str>>(rest) {
+            if after.is_empty() || after.starts_with(['\n', '\r']) {
+                break;
+            }
+            let (after, value) = item.parse(after).map_err(|e| match e {
+                nom::Err::Error(e) => nom::Err::Failure(e),
+                e => e,
+            })?;
+            items.push(value);
+            rest = after;
+        }
+
+        Ok((rest, items))
+    }
+}
+
+/// The empty line between two sections of an input.
+pub fn blank_line(input: &str) -> IResult<&str, ()> {
+    let (input, _) = pair(line_ending, line_ending)(input)?;
+    Ok((input, ()))
+}
+
+/// One or more `item`s split by blank lines, like day 13's claw machines.
+pub fn sections<'a, T>(
+    item: impl Parser<&'a str, T, nom::error::Error<&'a str>>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<T>> {
+    separated_list1(blank_line, item)
+}
+
+/// A rectangular-ish grid of characters, mapping each one with `cell`.
+///
+/// Fails on the first character `cell` rejects, so the error points at it.
+pub fn grid<T>(cell: impl Fn(char) -> Option<T>) -> impl Fn(&str) -> IResult<&str, Vec<Vec<T>>> {
+    move |input| {
+        let mut rows = Vec::new();
+        let mut rest = input;
+
+        while !rest.is_empty() && !rest.starts_with(['\n', '\r']) {
+            let end = rest.find(['\n', '\r']).unwrap_or(rest.len());
+            let mut row = Vec::with_capacity(end);
+            for (i, ch) in rest[..end].char_indices() {
+                match cell(ch) {
+                    Some(value) => row.push(value),
+                    None => {
+                        return Err(nom::Err::Error(nom::error::Error::new(
+                            &rest[i..],
+                            ErrorKind::Char,
+                        )));
+                    }
+                }
+            }
+            rows.push(row);
+            rest = &rest[end..];
+
+            // step over a single line ending, stopping at a blank line
+            match line_ending::<&str, nom::error::Error<&str>>(rest) {
+                Ok((after, _)) if !after.starts_with(['\n', '\r']) => rest = after,
+                _ => break,
+            }
+        }
+
+        if rows.is_empty() {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                ErrorKind::Many1,
+            )));
+        }
+        Ok((rest, rows))
+    }
+}
+
+/// A grid of raw characters.
+pub fn char_grid(input: &str) -> IResult<&str, Vec<Vec<char>>> {
+    grid(Some)(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_and_pairs() {
+        assert_eq!(finish("42", unsigned::<u32>), Ok(42));
+        assert_eq!(finish("-42", signed::<i64>), Ok(-42));
+        assert_eq!(finish("+7", signed::<i8>), Ok(7));
+        assert_eq!(finish("3,-4", number_pair::<i32>(",")), Ok((3, -4)));
+        assert_eq!(finish("p=0,4", key_pair::<usize>("p")), Ok((0, 4)));
+        assert_eq!(
+            finish("75,47,61", list(",", unsigned::<u8>)),
+            Ok(vec![75, 47, 61])
+        );
+    }
+
+    #[test]
+    fn lines_and_sections() {
+        let input = "1 2\n3 4\n\n5\n6\n";
+        let parser = separated_pair(
+            lines(separated_pair(unsigned::<u32>, char(' '), unsigned::<u32>)),
+            blank_line,
+            lines(unsigned::<u32>),
+        );
+        assert_eq!(
+            finish(input, parser),
+            Ok((vec![(1, 2), (3, 4)], vec![5, 6]))
+        );
+
+        assert_eq!(
+            finish("1\n2\n\n3\n", sections(lines(unsigned::<u32>))),
+            Ok(vec![vec![1, 2], vec![3]])
+        );
+        assert_eq!(
+            finish("#.\n.#\r\n", char_grid),
+            Ok(vec![vec!['#', '.'], vec!['.', '#']])
+        );
+    }
+
+    #[test]
+    fn error_locations() {
+        let err = finish("1 2\n3 4 x\n", lines(list(" ", unsigned::<u32>))).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 5));
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 5: unexpected trailing input"
+        );
+
+        let pair = || separated_pair(unsigned::<u32>, char(' '), unsigned::<u32>);
+        let err = finish("1 2\n3 4\n5 x\n", lines(pair())).unwrap_err();
+        assert_eq!((err.line, err.column), (3, 3));
+        assert_eq!(
+            err.to_string(),
+            "line 3, column 3: unexpected input (Digit)"
+        );
+
+        let err = finish("abc", unsigned::<u32>).unwrap_err();
+        assert_eq!((err.line, err.column), (1, 1));
+
+        let cell = |c| match c {
+            '#' => Some(true),
+            '.' => Some(false),
+            _ => None,
+        };
+        let err = finish("##\n#?.\n", grid(cell)).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 2));
+    }
+}
@@ -2,7 +2,55 @@ use std::process::{Command, Stdio};
 
 use crate::template::Day;
 
-pub fn handle(day: Day, release: bool, dhat: bool, submit_part: Option<u8>) {
+/// Flags handed on to the solution binary as they are.
+///
+/// - `--trace`: day 3 lists each instruction it runs, and day 17 prints its disassembly and
+///   a register trace.
+/// - `--svg`: day 12 draws its regions to `target/12.svg`.
+/// - `--dot`: day 24 writes its circuit to `target/24.dot`, suspect wires highlighted.
+/// - `--swaps`: day 24 explains each swap by the wiring rules it fixes.
+/// - `--picture`: day 14 saves and prints the tree.
+/// - `--operators`: day 7 prints the operators that solve each equation.
+/// - `--play`: day 15 steps through the warehouse interactively.
+/// - `--decompose`: day 19 lists one way to make each pattern.
+/// - `--cheats`: day 20 prints how many cheats save each amount of time.
+/// - `--buyers`: day 22 prints the best change sequence and each buyer's price.
+pub const SOLUTION_FLAGS: [&str; 10] = [
+    "--trace",
+    "--svg",
+    "--dot",
+    "--swaps",
+    "--picture",
+    "--operators",
+    "--play",
+    "--decompose",
+    "--cheats",
+    "--buyers",
+];
+
+/// Options handed on to the solution binary along with their value.
+///
+/// - `--visualize <ansi|ppm|pgm>` and `--stride <n>`: see `advent_of_code::visualize`.
+/// - `--blinks <n>`: day 11's blink count for part two, which also reports distinct values.
+/// - `--max-cheat-1 <n>`, `--min-saving-1 <n>` and the same for part 2: day 20's cheat
+///   duration and threshold, for that part only.
+pub const SOLUTION_OPTIONS: [&str; 7] = [
+    "--visualize",
+    "--stride",
+    "--blinks",
+    "--max-cheat-1",
+    "--min-saving-1",
+    "--max-cheat-2",
+    "--min-saving-2",
+];
+
+pub fn handle(
+    day: Day,
+    release: bool,
+    dhat: bool,
+    submit_part: Option<u8>,
+    solution_args: &[String],
+) {
     let mut cmd_args = vec!["run".to_string(), "--bin".to_string(), day.to_string()];
 
     if dhat {
@@ -23,6 +71,8 @@ pub fn handle(day: Day, release: bool, dhat: bool, submit_part: Option<u8>) {
         cmd_args.push(submit_part.to_string());
     }
 
+    cmd_args.extend_from_slice(solution_args);
+
     let mut cmd = Command::new("cargo")
         .args(&cmd_args)
         .stdout(Stdio::inherit())
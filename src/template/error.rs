@@ -0,0 +1,122 @@
+use std::error::Error;
+use std::fmt::{Display, Write};
+
+use crate::parse::{location, ParseError};
+use crate::template::Day;
+
+/// An error returned by a solution part, optionally pointing at a position in the input.
+///
+/// The day is filled in by the runner, so solutions can build these with [`SolutionError::new`]
+/// or by converting a [`ParseError`] with `?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolutionError {
+    pub day: Option<Day>,
+    /// 1-based `(line, column)` in the input.
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl SolutionError {
+    /// An error without a position in the input.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            day: None,
+            location: None,
+            message: message.into(),
+        }
+    }
+
+    /// An error at a byte offset of `input`.
+    pub fn at_offset(input: &str, offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            day: None,
+            location: Some(location(input, offset)),
+            message: message.into(),
+        }
+    }
+
+    /// An error at a 1-based line and column.
+    pub fn at_line(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            day: None,
+            location: Some((line, column)),
+            message: message.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_day(mut self, day: Day) -> Self {
+        self.day = Some(day);
+        self
+    }
+
+    /// Renders the error with the offending input line and a caret under the column.
+    pub fn report(&self, input: &str) -> String {
+        let mut report = self.to_string();
+
+        if let Some((line, column)) = self.location
+            && let Some(text) = input.lines().nth(line - 1)
+        {
+            let gutter = " ".repeat(line.to_string().len());
+            let _ = write!(
+                report,
+                "\n{gutter} |\n{line} | {text}\n{gutter} | {}^",
+                " ".repeat(column - 1)
+            );
+        }
+
+        report
+    }
+}
+
+impl Display for SolutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(day) = self.day {
+            write!(f, "Day {day}")?;
+            if self.location.is_some() {
+                write!(f, ", ")?;
+            }
+        }
+        if let Some((line, column)) = self.location {
+            write!(f, "line {line}, column {column}")?;
+        }
+        if self.day.is_some() || self.location.is_some() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for SolutionError {}
+
+impl From<ParseError> for SolutionError {
+    fn from(e: ParseError) -> Self {
+        Self::at_line(e.line, e.column, e.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day;
+
+    #[test]
+    fn display_and_report() {
+        let input = "..#.\n.x..\n";
+        let err = SolutionError::at_offset(input, 6, "unknown symbol 'x'").with_day(day!(6));
+
+        assert_eq!(err.location, Some((2, 2)));
+        assert_eq!(
+            err.to_string(),
+            "Day 06, line 2, column 2: unknown symbol 'x'"
+        );
+        assert_eq!(
+            err.report(input),
+            "Day 06, line 2, column 2: unknown symbol 'x'\n  |\n2 | .x..\n  |  ^"
+        );
+
+        let err = SolutionError::new("no guard position found in input");
+        assert_eq!(err.to_string(), "no guard position found in input");
+        assert_eq!(err.report(input), err.to_string());
+    }
+}
@@ -1,12 +1,19 @@
-use std::{env, fs};
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 pub mod aoc_cli;
 pub mod commands;
 pub mod runner;
 
 pub use day::*;
+pub use error::*;
 
 mod day;
+mod error;
 mod readme_benchmarks;
 mod run_multi;
 mod timings;
@@ -14,44 +21,148 @@ mod timings;
 pub const ANSI_ITALIC: &str = "\x1b[3m";
 pub const ANSI_BOLD: &str = "\x1b[1m";
 pub const ANSI_RESET: &str = "\x1b[0m";
+pub const ANSI_RED: &str = "\x1b[31m";
+
+/// Failure to read a day's input or example file.
+#[derive(Debug)]
+pub struct InputError {
+    pub path: PathBuf,
+    pub folder: String,
+    pub day: Day,
+    pub source: io::Error,
+}
+
+impl Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "could not open \"{}\": {}",
+            self.path.display(),
+            self.source
+        )?;
+        if self.source.kind() == io::ErrorKind::NotFound {
+            if self.folder == "inputs" {
+                write!(f, "\nRun `cargo download {}` to fetch it.", self.day)?;
+            } else {
+                write!(f, "\nRun `cargo scaffold {}` to create it.", self.day)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for InputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// The `data` directory of this crate, independent of the working directory.
+pub fn data_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data"))
+}
+
+/// Normalises CRLF line endings and makes sure non-empty input ends in a newline.
+pub fn normalize_input(raw: &str) -> String {
+    let mut input = raw.replace("\r\n", "\n");
+    if !input.is_empty() && !input.ends_with('\n') {
+        input.push('\n');
+    }
+    input
+}
+
+fn try_read(folder: &str, day: Day, file_name: &str) -> Result<String, InputError> {
+    let path = data_dir().join(folder).join(file_name);
+    let raw = fs::read_to_string(&path).map_err(|source| InputError {
+        path: path.clone(),
+        folder: folder.to_string(),
+        day,
+        source,
+    })?;
+
+    // `cargo scaffold` creates empty files, so this usually means it wasn't filled in yet.
+    if raw.trim().is_empty() {
+        if folder == "inputs" {
+            eprintln!(
+                "Warning: \"{}\" is empty. Run `cargo download {day}` to fetch it.",
+                path.display()
+            );
+        } else {
+            eprintln!("Warning: \"{}\" is empty.", path.display());
+        }
+    }
+
+    Ok(normalize_input(&raw))
+}
+
+/// Reads `data/<folder>/<day>.txt`, with line endings normalised.
+pub fn try_read_file(folder: &str, day: Day) -> Result<String, InputError> {
+    try_read(folder, day, &format!("{day}.txt"))
+}
+
+/// Reads `data/<folder>/<day>-<part>.txt`, with line endings normalised.
+pub fn try_read_file_part(folder: &str, day: Day, part: u8) -> Result<String, InputError> {
+    try_read(folder, day, &format!("{day}-{part}.txt"))
+}
 
 /// Helper function that reads a text file to a string.
+///
+/// # Panics
+/// If the file can't be read. See [`try_read_file`] for a fallible version.
 #[must_use]
 pub fn read_file(folder: &str, day: Day) -> String {
-    let cwd = env::current_dir().unwrap();
-    let filepath = cwd.join("data").join(folder).join(format!("{day}.txt"));
-    let f = fs::read_to_string(filepath);
-    f.expect("could not open input file")
+    try_read_file(folder, day).unwrap_or_else(|e| panic!("{e}"))
 }
 
 /// Helper function that reads a text file to string, appending a part suffix. E.g. like `01-2.txt`.
+///
+/// # Panics
+/// If the file can't be read. See [`try_read_file_part`] for a fallible version.
 #[must_use]
 pub fn read_file_part(folder: &str, day: Day, part: u8) -> String {
-    let cwd = env::current_dir().unwrap();
-    let filepath = cwd
-        .join("data")
-        .join(folder)
-        .join(format!("{day}-{part}.txt"));
-    let f = fs::read_to_string(filepath);
-    f.expect("could not open input file")
+    try_read_file_part(folder, day, part).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// The argument after `flag` on the solution's command line, if `flag` was passed.
+pub fn arg_str(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|a| a != flag);
+    args.next()?;
+    args.next()
+}
+
+/// The value after `flag` on the solution's command line, parsed. Passing the flag without a
+/// value that parses is an error, so a typo doesn't silently fall back to the default.
+pub fn arg_value<T: FromStr>(flag: &str) -> Result<Option<T>, SolutionError> {
+    if !std::env::args().any(|a| a == flag) {
+        return Ok(None);
+    }
+    arg_str(flag)
+        .and_then(|value| value.parse().ok())
+        .map(Some)
+        .ok_or_else(|| SolutionError::new(format!("{flag} expects a valid value")))
 }
 
 /// Creates the constant `DAY` and sets up the input and runner for each part.
 ///
 /// The optional, second parameter (1 or 2) allows you to only run a single part of the solution.
+/// Alternatively, `before: <fn(&str)>` is called once with the input ahead of both parts and
+/// outside their timings, for things like interactive modes.
 #[macro_export]
 macro_rules! solution {
     ($day:expr) => {
-        $crate::solution!(@impl $day, [part_one, 1] [part_two, 2]);
+        $crate::solution!(@impl $day, |_: &str| {}, [part_one, 1] [part_two, 2]);
     };
     ($day:expr, 1) => {
-        $crate::solution!(@impl $day, [part_one, 1]);
+        $crate::solution!(@impl $day, |_: &str| {}, [part_one, 1]);
     };
     ($day:expr, 2) => {
-        $crate::solution!(@impl $day, [part_two, 2]);
+        $crate::solution!(@impl $day, |_: &str| {}, [part_two, 2]);
+    };
+    ($day:expr, before: $before:expr) => {
+        $crate::solution!(@impl $day, $before, [part_one, 1] [part_two, 2]);
     };
 
-    (@impl $day:expr, $( [$func:expr, $part:expr] )*) => {
+    (@impl $day:expr, $before:expr, $( [$func:expr, $part:expr] )*) => {
         /// The current day.
         const DAY: $crate::template::Day = $crate::day!($day);
 
@@ -61,8 +172,42 @@ macro_rules! solution {
 
         fn main() {
             use $crate::template::runner::*;
-            let input = $crate::template::read_file("inputs", DAY);
+            let input = match $crate::template::try_read_file("inputs", DAY) {
+                Ok(input) => input,
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            };
+            ($before)(&input);
             $( run_part($func, &input, DAY, $part); )*
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day;
+
+    #[test]
+    fn normalizes_line_endings() {
+        assert_eq!(normalize_input("a\r\nb\r\n"), "a\nb\n");
+        assert_eq!(normalize_input("a\nb"), "a\nb\n");
+        assert_eq!(normalize_input(""), "");
+    }
+
+    #[test]
+    fn reads_relative_to_manifest() {
+        let example = try_read_file("examples", day!(1)).unwrap();
+        assert!(example.ends_with('\n'));
+        assert!(!example.contains('\r'));
+
+        let err = try_read_file_part("inputs", day!(1), 9).unwrap_err();
+        assert_eq!(err.source.kind(), io::ErrorKind::NotFound);
+        assert!(
+            err.to_string()
+                .ends_with("Run `cargo download 01` to fetch it.")
+        );
+    }
+}
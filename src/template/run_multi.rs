@@ -1,6 +1,6 @@
 use std::{collections::HashSet, io};
 
-use crate::template::{Day, ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
+use crate::template::{Day, ANSI_BOLD, ANSI_ITALIC, ANSI_RED, ANSI_RESET};
 
 use super::{
     all_days,
@@ -9,6 +9,7 @@ use super::{
 
 pub fn run_multi(days_to_run: &HashSet<Day>, is_release: bool, is_timed: bool) -> Option<Timings> {
     let mut timings: Vec<Timing> = Vec::with_capacity(days_to_run.len());
+    let mut errors: Vec<(Day, u8)> = vec![];
 
     let mut need_space = false;
 
@@ -31,9 +32,23 @@ pub fn run_multi(days_to_run: &HashSet<Day>, is_release: bool, is_timed: bool) -
             } else {
                 let val = child_commands::parse_exec_time(&output, day);
                 timings.push(val);
+                errors.extend(
+                    child_commands::parse_failed_parts(&output)
+                        .into_iter()
+                        .map(|part| (day, part)),
+                );
             }
         });
 
+    if !errors.is_empty() {
+        let failed = errors
+            .iter()
+            .map(|(day, part)| format!("Day {day} part {part}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("\n{ANSI_BOLD}{ANSI_RED}Errors:{ANSI_RESET} {failed}");
+    }
+
     if is_timed {
         let timings = Timings { data: timings };
         let total_millis = timings.total_millis();
@@ -68,7 +83,7 @@ pub fn get_path_for_bin(day: Day) -> String {
 /// This module encapsulates interaction with these binaries, both invoking them as well as parsing the timing output.
 pub mod child_commands {
     use super::{get_path_for_bin, Error};
-    use crate::template::Day;
+    use crate::template::{Day, ANSI_RED};
     use std::{
         io::{BufRead, BufReader},
         path::Path,
@@ -164,6 +179,17 @@ pub mod child_commands {
         timings
     }
 
+    /// Parts whose solution returned an error, as opposed to not being solved (`✖`).
+    pub fn parse_failed_parts(output: &[String]) -> Vec<u8> {
+        let marker = format!(": {ANSI_RED}error");
+
+        output
+            .iter()
+            .filter(|l| l.contains(&marker))
+            .filter_map(|l| l.strip_prefix("Part ")?.split(':').next()?.parse().ok())
+            .collect()
+    }
+
     fn parse_to_float(s: &str, postfix: &str) -> Option<f64> {
         s.split(postfix).next()?.parse().ok()
     }
@@ -174,7 +200,7 @@ pub mod child_commands {
             .split(" samples)")
             .next()?
             .split('(')
-            .last()?
+            .next_back()?
             .split('@')
             .next()?
             .trim();
@@ -205,7 +231,7 @@ pub mod child_commands {
 
     #[cfg(feature = "test_lib")]
     mod tests {
-        use super::parse_exec_time;
+        use super::{parse_exec_time, parse_failed_parts};
 
         use crate::day;
 
@@ -253,5 +279,16 @@ pub mod child_commands {
             assert_eq!(res.part_1.is_none(), true);
             assert_eq!(res.part_2.is_none(), true);
         }
+
+        #[test]
+        fn parses_failed_parts() {
+            let res = parse_failed_parts(&[
+                "Part 1: ✖        ".into(),
+                "Part 2: \x1b[31merror\x1b[0m        ".into(),
+                "Day 01, line 2, column 3: unexpected input (Digit)".into(),
+                "".into(),
+            ]);
+            assert_eq!(res, vec![2]);
+        }
     }
 }
@@ -6,19 +6,71 @@ use std::process::Output;
 use std::time::{Duration, Instant};
 use std::{cmp, env, process};
 
-use crate::template::ANSI_BOLD;
-use crate::template::{aoc_cli, Day, ANSI_ITALIC, ANSI_RESET};
+use crate::template::{aoc_cli, Day, SolutionError, ANSI_ITALIC, ANSI_RESET};
+use crate::template::{ANSI_BOLD, ANSI_RED};
+
+/// What a solution part produced, normalised from its return type.
+pub enum Outcome<T> {
+    Solved(T),
+    Unsolved,
+    Failed(SolutionError),
+}
+
+/// Return types a solution part may use: `Option<T>`, or `Result<T, E>` for parts that can fail.
+pub trait IntoOutcome {
+    type Output: Display;
+
+    fn into_outcome(self) -> Outcome<Self::Output>;
+}
 
-pub fn run_part<I: Clone, T: Display>(func: impl Fn(I) -> Option<T>, input: I, day: Day, part: u8) {
+impl<T: Display> IntoOutcome for Option<T> {
+    type Output = T;
+
+    fn into_outcome(self) -> Outcome<T> {
+        match self {
+            Some(result) => Outcome::Solved(result),
+            None => Outcome::Unsolved,
+        }
+    }
+}
+
+impl<T: Display, E: Into<SolutionError>> IntoOutcome for Result<T, E> {
+    type Output = T;
+
+    fn into_outcome(self) -> Outcome<T> {
+        match self {
+            Ok(result) => Outcome::Solved(result),
+            Err(e) => Outcome::Failed(e.into()),
+        }
+    }
+}
+
+pub fn run_part<I: Clone + AsRef<str>, R: IntoOutcome>(
+    func: impl Fn(I) -> R,
+    input: I,
+    day: Day,
+    part: u8,
+) {
     let part_str = format!("Part {part}");
+    let text = input.clone();
 
-    let (result, duration, samples) =
-        run_timed(func, input, |result| print_result(result, &part_str, ""));
+    let (result, duration, samples) = run_timed(
+        |input| func(input).into_outcome(),
+        input,
+        |result| print_result(result, &part_str, ""),
+    );
 
     print_result(&result, &part_str, &format_duration(&duration, samples));
 
-    if let Some(result) = result {
-        submit_result(result, day, part);
+    match result {
+        Outcome::Solved(result) => {
+            submit_result(result, day, part);
+        }
+        Outcome::Failed(e) => {
+            let e = if e.day.is_none() { e.with_day(day) } else { e };
+            println!("{}", e.report(text.as_ref()));
+        }
+        Outcome::Unsolved => {}
     }
 }
 
@@ -94,11 +146,11 @@ fn format_duration(duration: &Duration, samples: u128) -> String {
     }
 }
 
-fn print_result<T: Display>(result: &Option<T>, part: &str, duration_str: &str) {
+fn print_result<T: Display>(result: &Outcome<T>, part: &str, duration_str: &str) {
     let is_intermediate_result = duration_str.is_empty();
 
     match result {
-        Some(result) => {
+        Outcome::Solved(result) => {
             if result.to_string().contains('\n') {
                 let str = format!("{part}: ▼ {duration_str}");
                 if is_intermediate_result {
@@ -118,7 +170,7 @@ fn print_result<T: Display>(result: &Option<T>, part: &str, duration_str: &str)
                 }
             }
         }
-        None => {
+        Outcome::Unsolved => {
             if is_intermediate_result {
                 print!("{part}: ✖");
             } else {
@@ -126,6 +178,14 @@ fn print_result<T: Display>(result: &Option<T>, part: &str, duration_str: &str)
                 println!("{part}: ✖             ");
             }
         }
+        Outcome::Failed(_) => {
+            if is_intermediate_result {
+                print!("{part}: {ANSI_RED}error{ANSI_RESET}");
+            } else {
+                print!("\r");
+                println!("{part}: {ANSI_RED}error{ANSI_RESET}             ");
+            }
+        }
     }
 }
 
@@ -0,0 +1,418 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::hash::Hash;
+use std::io::{self, Write as _};
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use hashbrown::HashMap;
+
+use crate::template::{ANSI_RESET, Day, arg_str};
+
+pub type Rgb = [u8; 3];
+
+/// How one kind of cell is drawn: a glyph for the terminal and a colour for both outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub glyph: char,
+    pub color: Rgb,
+}
+
+/// A grid that can be drawn one cell at a time.
+///
+/// `Cell` is whatever distinguishes cells visually, and is looked up in a [`Palette`].
+pub trait Visualize {
+    type Cell: Copy + Eq + Hash;
+
+    /// `(width, height)` in cells.
+    fn size(&self) -> (usize, usize);
+
+    fn cell(&self, x: usize, y: usize) -> Self::Cell;
+}
+
+/// Maps each cell type to its [`Style`], falling back to a default for anything unlisted.
+#[derive(Debug, Clone)]
+pub struct Palette<C> {
+    styles: HashMap<C, Style>,
+    fallback: Style,
+}
+
+impl<C: Copy + Eq + Hash> Palette<C> {
+    pub fn new(glyph: char, color: Rgb) -> Self {
+        Self {
+            styles: HashMap::new(),
+            fallback: Style { glyph, color },
+        }
+    }
+
+    #[must_use]
+    pub fn with(mut self, cell: C, glyph: char, color: Rgb) -> Self {
+        self.styles.insert(cell, Style { glyph, color });
+        self
+    }
+
+    pub fn style(&self, cell: C) -> Style {
+        self.styles.get(&cell).copied().unwrap_or(self.fallback)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Redraws the grid in the terminal with 24-bit colour.
+    Ansi,
+    /// Writes colour `.ppm` images.
+    Ppm,
+    /// Writes greyscale `.pgm` images.
+    Pgm,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(Self::Ansi),
+            "ppm" => Ok(Self::Ppm),
+            "pgm" => Ok(Self::Pgm),
+            _ => Err(format!(
+                "unknown visualisation format {s:?}, expected ansi, ppm or pgm"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub format: Format,
+    /// Only every `stride`-th frame is drawn.
+    pub stride: usize,
+    /// Pixels per cell in images.
+    pub scale: usize,
+    /// Pause between terminal frames.
+    pub delay: Duration,
+}
+
+impl Options {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            stride: 1,
+            scale: 4,
+            delay: Duration::from_millis(30),
+        }
+    }
+
+    /// Reads `--visualize <ansi|ppm|pgm>` and an optional `--stride <n>` from the command line.
+    pub fn from_args() -> Option<Self> {
+        let format = match arg_str("--visualize")?.parse() {
+            Ok(format) => format,
+            Err(e) => {
+                eprintln!("{e}");
+                return None;
+            }
+        };
+        let mut options = Self::new(format);
+        if let Some(stride) = arg_str("--stride") {
+            match stride.parse::<usize>() {
+                Ok(stride) if stride > 0 => options.stride = stride,
+                _ => eprintln!("Ignoring invalid --stride {stride:?}."),
+            }
+        }
+        Some(options)
+    }
+}
+
+static SHOWN: Mutex<Vec<&'static Location<'static>>> = Mutex::new(Vec::new());
+
+/// True the first time the calling line runs in this process, and false after that.
+///
+/// `--time` runs each part many times over, so frames and printouts are put behind this to
+/// happen on the first run only. A helper called from both parts should be
+/// `#[track_caller]`, so each part gets its own first run.
+#[track_caller]
+pub fn first_run() -> bool {
+    let caller = Location::caller();
+    let mut shown = SHOWN.lock().unwrap_or_else(PoisonError::into_inner);
+    if shown.contains(&caller) {
+        return false;
+    }
+    shown.push(caller);
+    true
+}
+
+/// Whether `flag` was passed and this is the calling line's [`first_run`].
+#[track_caller]
+pub fn flag_once(flag: &str) -> bool {
+    std::env::args().any(|a| a == flag) && first_run()
+}
+
+/// Where image sequences for `day` are written: `target/vis/NN/`.
+pub fn frame_dir(day: Day) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/target/vis")).join(day.to_string())
+}
+
+/// Renders `grid` as coloured glyphs, one line per row.
+pub fn render_ansi<G: Visualize>(grid: &G, palette: &Palette<G::Cell>) -> String {
+    let (width, height) = grid.size();
+    let mut out = String::new();
+    for y in 0..height {
+        for x in 0..width {
+            let Style { glyph, color } = palette.style(grid.cell(x, y));
+            let _ = write!(
+                out,
+                "\x1b[38;2;{};{};{}m{glyph}",
+                color[0], color[1], color[2]
+            );
+        }
+        out.push_str(ANSI_RESET);
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders `grid` as a binary PPM, or a PGM if `grey`, with `scale` pixels per cell.
+pub fn render_image<G: Visualize>(
+    grid: &G,
+    palette: &Palette<G::Cell>,
+    scale: usize,
+    grey: bool,
+) -> Vec<u8> {
+    let (width, height) = grid.size();
+    let magic = if grey { "P5" } else { "P6" };
+    let mut image = format!("{magic}\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+
+    for y in 0..height {
+        let row: Vec<Rgb> = (0..width)
+            .map(|x| palette.style(grid.cell(x, y)).color)
+            .collect();
+        for _ in 0..scale {
+            for &[r, g, b] in &row {
+                for _ in 0..scale {
+                    if grey {
+                        image.push(luminance([r, g, b]));
+                    } else {
+                        image.extend([r, g, b]);
+                    }
+                }
+            }
+        }
+    }
+    image
+}
+
+/// Renders `grid` as a plain PBM bitmap, one pixel per cell, black where `on` is true.
+pub fn render_pbm<G: Visualize>(grid: &G, on: impl Fn(G::Cell) -> bool) -> Vec<u8> {
+    let (width, height) = grid.size();
+    let mut image = format!("P1\n{width} {height}\n");
+    for y in 0..height {
+        let row: Vec<&str> = (0..width)
+            .map(|x| if on(grid.cell(x, y)) { "1" } else { "0" })
+            .collect();
+        image.push_str(&row.join(" "));
+        image.push('\n');
+    }
+    image.into_bytes()
+}
+
+fn luminance([r, g, b]: Rgb) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+/// Draws frames of a simulation, skipping all but every `stride`-th.
+///
+/// Failing to write a frame prints a warning and turns the visualiser off, so a solution
+/// still gets its answer.
+#[derive(Debug)]
+pub struct Visualizer<C> {
+    palette: Palette<C>,
+    options: Options,
+    dir: PathBuf,
+    offered: usize,
+    written: usize,
+    enabled: bool,
+}
+
+impl<C: Copy + Eq + Hash> Visualizer<C> {
+    /// Writes images to `dir`, removing frames left there by an earlier run.
+    pub fn new(palette: Palette<C>, options: Options, dir: PathBuf) -> Self {
+        let mut visualizer = Self {
+            palette,
+            options,
+            dir,
+            offered: 0,
+            written: 0,
+            enabled: true,
+        };
+        if options.format != Format::Ansi
+            && let Err(e) = visualizer.prepare_dir()
+        {
+            visualizer.disable(e);
+        }
+        visualizer
+    }
+
+    /// A visualiser for `day` if `--visualize` was passed, see [`Options::from_args`]. Only
+    /// the [`first_run`] of the calling line gets one.
+    #[track_caller]
+    pub fn from_args(day: Day, palette: Palette<C>) -> Option<Self> {
+        let options = Options::from_args()?;
+        first_run().then(|| Self::new(palette, options, frame_dir(day)))
+    }
+
+    fn prepare_dir(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext == "ppm" || ext == "pgm")
+            {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn disable(&mut self, e: io::Error) {
+        eprintln!(
+            "Visualisation disabled, could not write to \"{}\": {e}",
+            self.dir.display()
+        );
+        self.enabled = false;
+    }
+
+    /// Offers a frame, which is drawn if it falls on the stride.
+    pub fn frame<G: Visualize<Cell = C>>(&mut self, grid: &G) {
+        self.offered += 1;
+        if (self.offered - 1).is_multiple_of(self.options.stride) {
+            self.draw(grid);
+        }
+    }
+
+    /// Draws the final state, unless [`Visualizer::frame`] just drew it.
+    pub fn finish<G: Visualize<Cell = C>>(&mut self, grid: &G) {
+        if self.offered == 0 || !(self.offered - 1).is_multiple_of(self.options.stride) {
+            self.draw(grid);
+        }
+        if self.options.format != Format::Ansi && self.enabled {
+            eprintln!(
+                "Wrote {} frames to \"{}\".",
+                self.written,
+                self.dir.display()
+            );
+        }
+    }
+
+    /// Number of frames drawn so far.
+    pub fn frames_written(&self) -> usize {
+        self.written
+    }
+
+    fn draw<G: Visualize<Cell = C>>(&mut self, grid: &G) {
+        if !self.enabled {
+            return;
+        }
+        let result = match self.options.format {
+            Format::Ansi => {
+                // clear the screen and move home, on stderr so answers on stdout stay parseable
+                let frame = render_ansi(grid, &self.palette);
+                let result = write!(io::stderr(), "\x1b[2J\x1b[H{frame}");
+                thread::sleep(self.options.delay);
+                result
+            }
+            Format::Ppm | Format::Pgm => {
+                let grey = self.options.format == Format::Pgm;
+                let extension = if grey { "pgm" } else { "ppm" };
+                let path = self.dir.join(format!("{:05}.{extension}", self.written));
+                fs::write(
+                    path,
+                    render_image(grid, &self.palette, self.options.scale, grey),
+                )
+            }
+        };
+        match result {
+            Ok(()) => self.written += 1,
+            Err(e) => self.disable(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Checkerboard(usize);
+
+    impl Visualize for Checkerboard {
+        type Cell = bool;
+
+        fn size(&self) -> (usize, usize) {
+            (self.0, 2)
+        }
+
+        fn cell(&self, x: usize, y: usize) -> bool {
+            (x + y).is_multiple_of(2)
+        }
+    }
+
+    fn palette() -> Palette<bool> {
+        Palette::new('.', [0, 0, 0]).with(true, '#', [255, 255, 255])
+    }
+
+    #[test]
+    fn renders_text_and_images() {
+        let ansi = render_ansi(&Checkerboard(2), &palette());
+        assert_eq!(
+            ansi,
+            "\x1b[38;2;255;255;255m#\x1b[38;2;0;0;0m.\x1b[0m\n\x1b[38;2;0;0;0m.\x1b[38;2;255;255;255m#\x1b[0m\n"
+        );
+
+        let ppm = render_image(&Checkerboard(2), &palette(), 1, false);
+        assert!(ppm.starts_with(b"P6\n2 2\n255\n"));
+        assert_eq!(ppm.len(), 11 + 2 * 2 * 3);
+
+        assert_eq!(
+            render_pbm(&Checkerboard(3), |c| c),
+            b"P1\n3 2\n1 0 1\n0 1 0\n"
+        );
+
+        let pgm = render_image(&Checkerboard(2), &palette(), 2, true);
+        assert_eq!(&pgm[..11], b"P5\n4 4\n255\n");
+        assert_eq!(&pgm[11..15], &[255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn first_run_is_per_line() {
+        let runs = |count| (0..count).map(|_| first_run()).collect::<Vec<_>>();
+        assert_eq!(runs(3), vec![true, false, false]);
+        assert!(first_run());
+        assert!(!flag_once("--not-passed"));
+    }
+
+    #[test]
+    fn writes_every_stride_frame() {
+        let dir = std::env::temp_dir().join("aoc-visualize-test");
+        let mut options = Options::new(Format::Pgm);
+        options.stride = 3;
+
+        let mut visualizer = Visualizer::new(palette(), options, dir.clone());
+        for width in 1..=8 {
+            visualizer.frame(&Checkerboard(width));
+        }
+        visualizer.finish(&Checkerboard(9));
+
+        // frames 1, 4 and 7 plus the final state
+        assert_eq!(visualizer.frames_written(), 4);
+        assert!(dir.join("00003.pgm").exists());
+
+        // a new run clears the old frames
+        let visualizer = Visualizer::new(palette(), options, dir.clone());
+        assert_eq!(visualizer.frames_written(), 0);
+        assert!(!dir.join("00000.pgm").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}